@@ -0,0 +1,300 @@
+//
+
+use crate::calendar;
+use crate::iex::Stock;
+use crate::storage;
+use crate::utils::inline_code;
+use crate::Database;
+use crate::IEXClient;
+
+use chrono::Utc;
+use serenity::{
+    http::Http,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::{RwLock, TypeMap},
+};
+use tokio::time::delay_for;
+
+use log::{debug, error};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+/// Most alerts a single user may have armed at once
+pub const MAX_ALERTS: usize = 25;
+
+/// What an alert waits for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    /// Price at or above the value
+    Above(f64),
+    /// Price at or below the value
+    Below(f64),
+    /// Day change of at least this many percent, in either direction
+    Change(f64),
+}
+
+impl Condition {
+    /// Parses the `above 200`, `below 150` or `change 5%` part of an alert
+    pub fn parse(kind: &str, value: &str) -> Result<Self, String> {
+        let number = |v: &str| {
+            v.trim_start_matches('$')
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n > 0.0)
+                .ok_or_else(|| {
+                    format!("Invalid value: {}", inline_code(value))
+                })
+        };
+
+        match kind.to_lowercase().as_str() {
+            "above" | ">" => Ok(Condition::Above(number(value)?)),
+            "below" | "<" => Ok(Condition::Below(number(value)?)),
+            "change" | "move" => Ok(Condition::Change(number(value)?)),
+            _ => Err(format!(
+                "Unknown condition {}, use above, below or change",
+                inline_code(kind)
+            )),
+        }
+    }
+
+    /// Rebuilds a condition from its stored `kind` and `value`
+    pub fn from_parts(kind: &str, value: f64) -> Option<Self> {
+        match kind {
+            "above" => Some(Condition::Above(value)),
+            "below" => Some(Condition::Below(value)),
+            "change" => Some(Condition::Change(value)),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Condition::Above(_) => "above",
+            Condition::Below(_) => "below",
+            Condition::Change(_) => "change",
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        match *self {
+            Condition::Above(v)
+            | Condition::Below(v)
+            | Condition::Change(v) => v,
+        }
+    }
+
+    /// Whether the condition holds for a price and its day change, where
+    /// the change is a fraction as reported by IEX
+    pub fn is_met(&self, price: f64, change_percent: f64) -> bool {
+        match *self {
+            Condition::Above(v) => price >= v,
+            Condition::Below(v) => price <= v,
+            Condition::Change(v) => change_percent.abs() * 100.0 >= v,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Above(v) => write!(f, "above {:.2}", v),
+            Condition::Below(v) => write!(f, "below {:.2}", v),
+            Condition::Change(v) => write!(f, "change of {}%", v),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Alert {
+    pub id: u64,
+    pub user: UserId,
+    pub guild: Option<GuildId>,
+    /// Where to mention the user, or `None` to send a DM
+    pub channel: Option<ChannelId>,
+    pub symbol: String,
+    pub condition: Condition,
+    /// Whether the condition held when last evaluated, `None` until then
+    pub met: Option<bool>,
+}
+
+impl Alert {
+    /// Whether the alert fires now that its condition is `met` or not
+    ///
+    /// Alerts fire when the condition starts to hold, so one set while it
+    /// already holds waits for the price to cross back and again.
+    pub fn fires(&self, met: bool) -> bool {
+        met && self.met == Some(false)
+    }
+}
+
+async fn notify(http: &Arc<Http>, alert: &Alert, price: f64) {
+    let text = format!(
+        "Alert #{} triggered: {} {} (now {:.2})",
+        alert.id, alert.symbol, alert.condition, price
+    );
+
+    let result = match alert.channel {
+        Some(channel) => {
+            channel
+                .say(http, format!("<@{}> {}", alert.user.0, text))
+                .await
+        }
+        None => match alert.user.create_dm_channel(http).await {
+            Ok(dm) => dm.say(http, text).await,
+            Err(why) => Err(why),
+        },
+    };
+
+    if let Err(why) = result {
+        error!("Could not deliver alert #{}: {:?}", alert.id, why);
+    }
+}
+
+async fn check(http: &Arc<Http>, data: &Arc<RwLock<TypeMap>>) {
+    let (client, db) = {
+        let data = data.read().await;
+
+        let client = match data.get::<IEXClient>() {
+            Some(c) => c.clone(),
+            None => {
+                error!("Could not get iex client");
+                return;
+            }
+        };
+
+        match data.get::<Database>() {
+            Some(db) => (client, db.clone()),
+            None => {
+                error!("Could not get database");
+                return;
+            }
+        }
+    };
+
+    let symbols =
+        match storage::blocking(db.clone(), |db| db.alert_symbols()).await {
+            Ok(s) => s,
+            Err(why) => {
+                error!("Could not load alerts: {:?}", why);
+                return;
+            }
+        };
+
+    if symbols.is_empty() {
+        return;
+    }
+
+    let request: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+    let prices: HashMap<String, (f64, f64)> =
+        match client.quotes(&request).await {
+            Ok(quotes) => quotes
+                .into_iter()
+                .map(|(s, q)| (s, (q.price, q.change_percent)))
+                .collect(),
+            Err(why) => {
+                error!("Could not get quotes for alerts: {:?}", why);
+                return;
+            }
+        };
+
+    let triggered = match storage::blocking(db, move |db| {
+        db.take_triggered_alerts(&prices)
+    })
+    .await
+    {
+        Ok(t) => t,
+        Err(why) => {
+            error!("Could not save alerts: {:?}", why);
+            return;
+        }
+    };
+
+    for (alert, price) in triggered {
+        debug!("Alert #{} triggered at {}", alert.id, price);
+        notify(http, &alert, price).await;
+    }
+}
+
+/// Evaluates armed alerts every `interval` while the market is open
+pub async fn poll(
+    http: Arc<Http>,
+    data: Arc<RwLock<TypeMap>>,
+    interval: Duration,
+) {
+    loop {
+        delay_for(interval).await;
+
+        if calendar::is_regular_hours(Utc::now()) {
+            check(&http, &data).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Alert, Condition};
+    use serenity::model::id::UserId;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        kind, value, result,
+        case::above("above", "200", Ok(Condition::Above(200.0))),
+        case::above_dollar("above", "$200.50", Ok(Condition::Above(200.5))),
+        case::below("below", "150", Ok(Condition::Below(150.0))),
+        case::below_symbol("<", "150", Ok(Condition::Below(150.0))),
+        case::change("change", "5%", Ok(Condition::Change(5.0))),
+        case::change_bare("CHANGE", "5", Ok(Condition::Change(5.0))),
+        case::negative("above", "-1", Err("Invalid value: `-1`".to_string())),
+        case::garbage("above", "lots", Err(
+            "Invalid value: `lots`".to_string())),
+        case::mention("above", "@everyone", Err(
+            "Invalid value: `@everyone`".to_string())),
+        case::unknown("sideways", "5", Err(
+            "Unknown condition `sideways`, use above, below or change"
+                .to_string())),
+    )]
+    fn parse(kind: &str, value: &str, result: Result<Condition, String>) {
+        assert_eq!(Condition::parse(kind, value), result);
+    }
+
+    #[rstest(
+        condition, price, change, result,
+        case::above(Condition::Above(200.0), 201.0, 0.0, true),
+        case::above_equal(Condition::Above(200.0), 200.0, 0.0, true),
+        case::above_not(Condition::Above(200.0), 199.0, 0.0, false),
+        case::below(Condition::Below(150.0), 149.0, 0.0, true),
+        case::below_not(Condition::Below(150.0), 151.0, 0.0, false),
+        case::change_up(Condition::Change(5.0), 10.0, 0.06, true),
+        case::change_down(Condition::Change(5.0), 10.0, -0.05, true),
+        case::change_not(Condition::Change(5.0), 10.0, 0.049, false),
+    )]
+    fn is_met(condition: Condition, price: f64, change: f64, result: bool) {
+        assert_eq!(condition.is_met(price, change), result);
+    }
+
+    #[rstest(
+        last,
+        met,
+        result,
+        case::crossed(Some(false), true, true),
+        case::still_met(Some(true), true, false),
+        case::already_met(None, true, false),
+        case::not_met(Some(false), false, false),
+        case::fell_back(Some(true), false, false)
+    )]
+    fn fires(last: Option<bool>, met: bool, result: bool) {
+        let alert = Alert {
+            id: 1,
+            user: UserId(1),
+            guild: None,
+            channel: None,
+            symbol: "AAPL".to_string(),
+            condition: Condition::Above(200.0),
+            met: last,
+        };
+
+        assert_eq!(alert.fires(met), result);
+    }
+}
@@ -0,0 +1,294 @@
+//
+
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::America::New_York;
+
+use std::fmt;
+
+/// Part of the trading day US equities are in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Session {
+    PreMarket,
+    Regular,
+    AfterHours,
+    Closed,
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            Session::PreMarket => "Pre-market",
+            Session::Regular => "Regular hours",
+            Session::AfterHours => "After hours",
+            Session::Closed => "Closed",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+fn pre_market_open() -> NaiveTime {
+    NaiveTime::from_hms(4, 0, 0)
+}
+
+fn regular_open() -> NaiveTime {
+    NaiveTime::from_hms(9, 30, 0)
+}
+
+/// When the regular session ends on a trading day
+fn regular_close(date: NaiveDate) -> NaiveTime {
+    if is_early_close(date) {
+        NaiveTime::from_hms(13, 0, 0)
+    } else {
+        NaiveTime::from_hms(16, 0, 0)
+    }
+}
+
+/// When after hours trading ends on a trading day
+fn after_hours_close(date: NaiveDate) -> NaiveTime {
+    if is_early_close(date) {
+        NaiveTime::from_hms(17, 0, 0)
+    } else {
+        NaiveTime::from_hms(20, 0, 0)
+    }
+}
+
+/// Easter Sunday, by the anonymous Gregorian algorithm
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let n = h + l - 7 * m + 114;
+
+    NaiveDate::from_ymd(year, (n / 31) as u32, (n % 31 + 1) as u32)
+}
+
+/// The last `weekday` of the month
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    let mut date = NaiveDate::from_weekday_of_month(year, month, weekday, 4);
+    while (date + Duration::weeks(1)).month() == month {
+        date += Duration::weeks(1);
+    }
+    date
+}
+
+/// Fixed date holidays falling on a weekend are observed on the nearest
+/// weekday
+fn observed(year: i32, month: u32, day: u32) -> NaiveDate {
+    let date = NaiveDate::from_ymd(year, month, day);
+    match date.weekday() {
+        Weekday::Sat => date.pred(),
+        Weekday::Sun => date.succ(),
+        _ => date,
+    }
+}
+
+/// The NYSE holiday observed on `date`, if any
+pub fn holiday(date: NaiveDate) -> Option<&'static str> {
+    let year = date.year();
+
+    // New Year's Day on a Saturday is not observed on the Friday before,
+    // that would close the exchange on the last day of the year
+    let new_year = NaiveDate::from_ymd(year, 1, 1);
+    let new_year = if new_year.weekday() == Weekday::Sun {
+        new_year.succ()
+    } else {
+        new_year
+    };
+
+    let mut holidays = vec![
+        (new_year, "New Year's Day"),
+        (
+            NaiveDate::from_weekday_of_month(year, 1, Weekday::Mon, 3),
+            "Martin Luther King Jr. Day",
+        ),
+        (
+            NaiveDate::from_weekday_of_month(year, 2, Weekday::Mon, 3),
+            "Washington's Birthday",
+        ),
+        (easter(year) - Duration::days(2), "Good Friday"),
+        (last_weekday(year, 5, Weekday::Mon), "Memorial Day"),
+        (observed(year, 7, 4), "Independence Day"),
+        (
+            NaiveDate::from_weekday_of_month(year, 9, Weekday::Mon, 1),
+            "Labor Day",
+        ),
+        (
+            NaiveDate::from_weekday_of_month(year, 11, Weekday::Thu, 4),
+            "Thanksgiving Day",
+        ),
+        (observed(year, 12, 25), "Christmas Day"),
+    ];
+
+    if year >= 2022 {
+        holidays.push((observed(year, 6, 19), "Juneteenth"));
+    }
+
+    holidays
+        .into_iter()
+        .find(|(d, _)| *d == date)
+        .map(|(_, name)| name)
+}
+
+/// Whether the exchange is open at all on `date`
+pub fn is_trading_day(date: NaiveDate) -> bool {
+    match date.weekday() {
+        Weekday::Sat | Weekday::Sun => false,
+        _ => holiday(date).is_none(),
+    }
+}
+
+/// Whether the regular session ends at 1pm on `date`, as it does before
+/// Independence Day, after Thanksgiving and on Christmas Eve
+pub fn is_early_close(date: NaiveDate) -> bool {
+    if !is_trading_day(date) {
+        return false;
+    }
+
+    let year = date.year();
+    let thanksgiving =
+        NaiveDate::from_weekday_of_month(year, 11, Weekday::Thu, 4);
+
+    date == NaiveDate::from_ymd(year, 7, 3)
+        || date == thanksgiving.succ()
+        || date == NaiveDate::from_ymd(year, 12, 24)
+}
+
+/// The session US equities are in at `now`
+pub fn session(now: DateTime<Utc>) -> Session {
+    let local = now.with_timezone(&New_York);
+    let date = local.date().naive_local();
+    let time = local.time();
+
+    if !is_trading_day(date) || time < pre_market_open() {
+        Session::Closed
+    } else if time < regular_open() {
+        Session::PreMarket
+    } else if time < regular_close(date) {
+        Session::Regular
+    } else if time < after_hours_close(date) {
+        Session::AfterHours
+    } else {
+        Session::Closed
+    }
+}
+
+/// Whether US equities are in their regular trading session
+pub fn is_regular_hours(now: DateTime<Utc>) -> bool {
+    session(now) == Session::Regular
+}
+
+/// New York wall clock time on `date` as an instant
+fn at(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    New_York
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .expect("Market hours are never skipped by DST")
+        .with_timezone(&Utc)
+}
+
+/// The first trading day on or after `date`
+fn next_trading_day(mut date: NaiveDate) -> NaiveDate {
+    while !is_trading_day(date) {
+        date = date.succ();
+    }
+    date
+}
+
+/// When the next regular session after `now` opens
+pub fn next_open(now: DateTime<Utc>) -> DateTime<Utc> {
+    let local = now.with_timezone(&New_York);
+    let today = local.date().naive_local();
+
+    let date = if is_trading_day(today) && local.time() < regular_open() {
+        today
+    } else {
+        next_trading_day(today.succ())
+    };
+
+    at(date, regular_open())
+}
+
+/// When the current regular session closes, or the next one if the
+/// market is not open
+pub fn next_close(now: DateTime<Utc>) -> DateTime<Utc> {
+    let local = now.with_timezone(&New_York);
+    let today = local.date().naive_local();
+
+    let date = if is_trading_day(today) && local.time() < regular_close(today)
+    {
+        today
+    } else {
+        next_trading_day(today.succ())
+    };
+
+    at(date, regular_close(date))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Session;
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        date,
+        result,
+        case::good_friday("2021-04-02", Some("Good Friday")),
+        case::memorial("2021-05-31", Some("Memorial Day")),
+        case::independence_sunday("2021-07-05", Some("Independence Day")),
+        case::christmas_saturday("2021-12-24", Some("Christmas Day")),
+        case::new_year_saturday("2021-12-31", None),
+        case::juneteenth_sunday("2022-06-20", Some("Juneteenth")),
+        case::juneteenth_before("2021-06-18", None),
+        case::thanksgiving("2020-11-26", Some("Thanksgiving Day")),
+        case::trading("2020-11-27", None)
+    )]
+    fn holiday(date: &str, result: Option<&str>) {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        assert_eq!(super::holiday(date), result);
+    }
+
+    #[rstest(
+        time,
+        result,
+        case::overnight("2020-11-30T08:00:00Z", Session::Closed),
+        case::pre_market("2020-11-30T14:00:00Z", Session::PreMarket),
+        case::open("2020-11-30T14:30:00Z", Session::Regular),
+        case::after_hours("2020-11-30T21:00:00Z", Session::AfterHours),
+        case::early_close("2020-11-27T18:30:00Z", Session::AfterHours),
+        case::summer("2020-07-06T13:30:00Z", Session::Regular),
+        case::weekend("2020-11-28T15:00:00Z", Session::Closed),
+        case::holiday("2020-11-26T15:00:00Z", Session::Closed)
+    )]
+    fn session(time: &str, result: Session) {
+        let now = time.parse().unwrap();
+        assert_eq!(super::session(now), result);
+    }
+
+    #[test]
+    fn next_open() {
+        // Thursday evening before Good Friday opens on Monday
+        let now = Utc.ymd(2021, 4, 1).and_hms(22, 0, 0);
+        assert_eq!(
+            super::next_open(now),
+            Utc.ymd(2021, 4, 5).and_hms(13, 30, 0)
+        );
+        assert_eq!(
+            super::next_close(now),
+            Utc.ymd(2021, 4, 5).and_hms(20, 0, 0)
+        );
+    }
+}
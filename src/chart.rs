@@ -0,0 +1,261 @@
+//
+
+use crate::iex::{ChartPoint, ChartRange};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use image::{png::PngEncoder, ColorType};
+use plotters::prelude::*;
+use tokio::task;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 450;
+
+/// Ranges with more bars than this are drawn as a line instead of candles
+const MAX_CANDLES: usize = 90;
+
+const BACKGROUND: RGBColor = RGBColor(0x2f, 0x31, 0x36);
+const FOREGROUND: RGBColor = RGBColor(0xdc, 0xdd, 0xde);
+const GRID: RGBColor = RGBColor(0x40, 0x44, 0x4b);
+const UP: RGBColor = RGBColor(0x43, 0xb5, 0x81);
+const DOWN: RGBColor = RGBColor(0xf0, 0x47, 0x47);
+
+/// A chart bar with every price present
+struct Bar {
+    label: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+fn label(point: &ChartPoint, range: ChartRange) -> String {
+    if range.is_intraday() {
+        if let Some(minute) = &point.minute {
+            return minute.clone();
+        }
+    }
+
+    match NaiveDate::parse_from_str(&point.date, "%Y-%m-%d") {
+        Ok(date) => match range {
+            ChartRange::OneYear | ChartRange::FiveYears => {
+                date.format("%b %Y").to_string()
+            }
+            _ => date.format("%b %d").to_string(),
+        },
+        Err(_) => point.date.clone(),
+    }
+}
+
+fn bars(points: &[ChartPoint], range: ChartRange) -> Vec<Bar> {
+    points
+        .iter()
+        .filter_map(|p| {
+            // Intraday minutes without trades come back without prices
+            let close = p.close?;
+
+            Some(Bar {
+                label: label(p, range),
+                open: p.open.unwrap_or(close),
+                high: p.high.unwrap_or(close),
+                low: p.low.unwrap_or(close),
+                close,
+            })
+        })
+        .collect()
+}
+
+/// Runs `render` on the blocking thread pool, so plotting and encoding
+/// the image does not stall the async runtime
+pub async fn render_blocking(
+    symbol: String,
+    range: ChartRange,
+    points: Vec<ChartPoint>,
+) -> Result<Option<Vec<u8>>> {
+    task::spawn_blocking(move || render(&symbol, range, &points)).await?
+}
+
+/// Renders the chart points as a PNG image, or `None` if none of them
+/// have a price
+///
+/// Short daily ranges are drawn as candlesticks, intraday and long
+/// ranges as a closing price line.
+fn render(
+    symbol: &str,
+    range: ChartRange,
+    points: &[ChartPoint],
+) -> Result<Option<Vec<u8>>> {
+    let bars = bars(points, range);
+
+    if bars.is_empty() {
+        return Ok(None);
+    }
+
+    let low = bars.iter().map(|b| b.low).fold(f64::INFINITY, f64::min);
+    let high = bars
+        .iter()
+        .map(|b| b.high)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let pad = ((high - low) * 0.05).max(0.01);
+
+    let first = bars[0].close;
+    let last = bars[bars.len() - 1].close;
+    let colour = if last >= first { UP } else { DOWN };
+
+    let mut buffer = vec![0; (WIDTH * HEIGHT * 3) as usize];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT))
+            .into_drawing_area();
+        root.fill(&BACKGROUND)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                format!("{} ({})", symbol, range),
+                ("sans-serif", 24).into_font().color(&FOREGROUND),
+            )
+            .margin(12)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(0..bars.len(), (low - pad)..(high + pad))?;
+
+        chart
+            .configure_mesh()
+            .light_line_style(&BACKGROUND)
+            .bold_line_style(&GRID)
+            .axis_style(&FOREGROUND)
+            .label_style(("sans-serif", 14).into_font().color(&FOREGROUND))
+            .x_labels(6)
+            .x_label_formatter(&|i| {
+                bars.get(*i).map(|b| b.label.clone()).unwrap_or_default()
+            })
+            .y_label_formatter(&|p| format!("{:.2}", p))
+            .draw()?;
+
+        if range.is_intraday() || bars.len() > MAX_CANDLES {
+            chart.draw_series(LineSeries::new(
+                bars.iter().enumerate().map(|(i, b)| (i, b.close)),
+                colour.stroke_width(2),
+            ))?;
+        } else {
+            let width = (WIDTH as usize * 3 / 5 / bars.len()).max(1) as u32;
+
+            chart.draw_series(bars.iter().enumerate().map(|(i, b)| {
+                CandleStick::new(
+                    i,
+                    b.open,
+                    b.high,
+                    b.low,
+                    b.close,
+                    UP.filled(),
+                    DOWN.filled(),
+                    width,
+                )
+            }))?;
+        }
+
+        root.present()?;
+    }
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).encode(
+        &buffer,
+        WIDTH,
+        HEIGHT,
+        ColorType::Rgb8,
+    )?;
+
+    Ok(Some(png))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bars, label, render_blocking};
+    use crate::iex::{ChartPoint, ChartRange};
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn point(
+        date: &str,
+        minute: Option<&str>,
+        open: Option<f64>,
+        close: Option<f64>,
+    ) -> ChartPoint {
+        ChartPoint {
+            date: date.to_string(),
+            minute: minute.map(|m| m.to_string()),
+            open,
+            high: None,
+            low: None,
+            close,
+            volume: None,
+        }
+    }
+
+    #[rstest(
+        date,
+        minute,
+        range,
+        result,
+        case::minute(
+            "2020-08-14",
+            Some("09:30"),
+            ChartRange::OneDay,
+            "09:30"
+        ),
+        case::day("2020-08-14", None, ChartRange::OneMonth, "Aug 14"),
+        case::day_minute(
+            "2020-08-14",
+            Some("09:30"),
+            ChartRange::FiveDays,
+            "Aug 14"
+        ),
+        case::month("2020-08-14", None, ChartRange::OneYear, "Aug 2020"),
+        case::years("2016-01-04", None, ChartRange::FiveYears, "Jan 2016"),
+        case::invalid("20200814", None, ChartRange::OneMonth, "20200814")
+    )]
+    fn labels(
+        date: &str,
+        minute: Option<&str>,
+        range: ChartRange,
+        result: &str,
+    ) {
+        assert_eq!(label(&point(date, minute, None, None), range), result);
+    }
+
+    #[test]
+    fn bars_without_prices() {
+        let points = vec![
+            point("2020-08-14", Some("09:30"), Some(10.0), Some(11.0)),
+            point("2020-08-14", Some("09:31"), None, None),
+            point("2020-08-14", Some("09:32"), None, Some(12.0)),
+        ];
+
+        let bars = bars(&points, ChartRange::OneDay);
+        let bars: Vec<_> = bars
+            .iter()
+            .map(|b| (b.label.as_str(), b.open, b.high, b.low, b.close))
+            .collect();
+
+        assert_eq!(
+            bars,
+            vec![
+                ("09:30", 10.0, 11.0, 11.0, 11.0),
+                ("09:32", 12.0, 12.0, 12.0, 12.0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn render_without_prices() {
+        let points = vec![point("2020-08-14", None, None, None)];
+
+        let png =
+            render_blocking("AAPL".to_string(), ChartRange::OneMonth, points)
+                .await
+                .unwrap();
+
+        assert_eq!(png, None);
+    }
+}
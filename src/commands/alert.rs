@@ -0,0 +1,236 @@
+//
+
+use super::utils;
+
+use crate::alerts::{Condition, MAX_ALERTS};
+use crate::iex;
+use crate::storage;
+use crate::utils::{inline_code, normalize_symbol};
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+#[command]
+#[sub_commands(cancel)]
+#[usage("SYMBOL above|below|change VALUE [dm]")]
+#[example("AAPL above 200")]
+#[example("NVDA change 5% dm")]
+#[min_args(3)]
+#[max_args(4)]
+async fn alert(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let symbol = normalize_symbol(&args.single::<String>()?);
+    let kind = args.single::<String>()?;
+    let value = args.single::<String>()?;
+    let dm = match args.single::<String>() {
+        Ok(flag) if flag.eq_ignore_ascii_case("dm") => true,
+        Ok(flag) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!(
+                        "Unknown option {}, did you mean dm?",
+                        inline_code(&flag)
+                    ))
+                })
+                .await?;
+            return Ok(());
+        }
+        Err(_) => msg.guild_id.is_none(),
+    };
+
+    let condition = match Condition::parse(&kind, &value) {
+        Ok(c) => c,
+        Err(why) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content(why))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let symbol = match iex::cache::universe() {
+        Some(universe) => match utils::resolve_listed(&universe, &symbol) {
+            Some(s) => s,
+            None => {
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        m.content(format!("{}: {}", utils::UNKNOWN, symbol))
+                    })
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Alerts fire on crossing their threshold, so note which side of it
+    // the price is on now
+    let met = match utils::iex_client(ctx).await {
+        Some(client) => {
+            match iex::cache::quotes(&client, &[symbol.as_str()]).await {
+                Ok(quotes) => quotes
+                    .get(&symbol)
+                    .map(|q| condition.is_met(q.price, q.change_percent)),
+                Err(why) => {
+                    error!("Could not get quote for {}: {:?}", symbol, why);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let channel = if dm { None } else { Some(msg.channel_id) };
+    let alerted = symbol.clone();
+    let id = storage::blocking(db, move |db| {
+        if db.alerts_for_user(user)?.len() >= MAX_ALERTS {
+            Ok(None)
+        } else {
+            db.add_alert(user, guild, channel, &alerted, condition, met)
+                .map(Some)
+        }
+    })
+    .await?;
+
+    let content = match id {
+        Some(id) => format!(
+            "Alert #{} set: {} {}{}{}",
+            id,
+            symbol,
+            condition,
+            if dm { ", I'll DM you" } else { "" },
+            if met == Some(true) {
+                "\nIt already is, so the alert waits for it to cross back \
+                 and again"
+            } else {
+                ""
+            }
+        ),
+        None => format!(
+            "You already have {} alerts, cancel one with `!alert cancel ID`",
+            MAX_ALERTS
+        ),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[aliases(rm, remove)]
+#[usage("ID")]
+#[num_args(1)]
+async fn cancel(
+    ctx: &Context,
+    msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    let id = args.single::<String>()?;
+    let id = match id.trim_start_matches('#').parse::<u64>() {
+        Ok(id) => id,
+        Err(_) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!(
+                        "Invalid alert id: {}",
+                        inline_code(&id)
+                    ))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let user = msg.author.id;
+    let cancelled =
+        storage::blocking(db, move |db| db.cancel_alert(user, id)).await?;
+
+    let content = match cancelled {
+        Some(a) => {
+            format!("Cancelled alert #{}: {} {}", a.id, a.symbol, a.condition)
+        }
+        None => format!("You have no alert #{}", id),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
+
+#[command]
+async fn alerts(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let user = msg.author.id;
+    let lines: Vec<String> =
+        storage::blocking(db, move |db| db.alerts_for_user(user))
+            .await?
+            .iter()
+            .map(|a| {
+                format!(
+                    "#{} {} {}{}",
+                    a.id,
+                    a.symbol,
+                    a.condition,
+                    if a.channel.is_none() { " (DM)" } else { "" }
+                )
+            })
+            .collect();
+
+    let content = if lines.is_empty() {
+        "You have no alerts, set one with `!alert SYMBOL above VALUE`"
+            .to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
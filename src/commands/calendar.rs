@@ -0,0 +1,72 @@
+//
+
+use super::utils::format_time;
+
+use crate::calendar::{self, Session};
+
+use chrono::{Duration, Utc};
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+/// Formats a duration to the minute, e.g. `1d 2h 5m`
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) =
+        (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[command]
+async fn market(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let now = Utc::now();
+    let today = now.with_timezone(&chrono_tz::America::New_York).date();
+
+    let content = match calendar::session(now) {
+        Session::Regular => {
+            let close = calendar::next_close(now);
+            format!(
+                "The market is open, it closes {}at {} (in {})",
+                if calendar::is_early_close(today.naive_local()) {
+                    "early "
+                } else {
+                    ""
+                },
+                format_time(close, now),
+                format_duration(close - now)
+            )
+        }
+        session => {
+            let open = calendar::next_open(now);
+            let status = match session {
+                Session::PreMarket => "in pre-market trading".to_string(),
+                Session::AfterHours => "in after hours trading".to_string(),
+                _ => match calendar::holiday(today.naive_local()) {
+                    Some(holiday) => format!("closed for {}", holiday),
+                    None => "closed".to_string(),
+                },
+            };
+
+            format!(
+                "The market is {}, it opens at {} (in {})",
+                status,
+                format_time(open, now),
+                format_duration(open - now)
+            )
+        }
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
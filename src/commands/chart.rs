@@ -0,0 +1,140 @@
+//
+
+use super::utils;
+
+use crate::chart;
+use crate::iex;
+use crate::iex::{ChartRange, Stock};
+use crate::utils::{inline_code, normalize_symbol};
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Parses a chart range argument, erroring with the reply to send
+fn parse_range(arg: &str) -> Result<ChartRange, String> {
+    arg.parse::<ChartRange>()
+        .map_err(|why| format!("Unknown chart range: {}", inline_code(&why.0)))
+}
+
+#[command]
+#[usage("SYMBOL [1d|5d|1m|3m|6m|ytd|1y|5y]")]
+#[min_args(1)]
+#[max_args(2)]
+async fn chart(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let symbol = normalize_symbol(&args.single::<String>()?);
+
+    let range = if args.is_empty() {
+        ChartRange::default()
+    } else {
+        match parse_range(&args.single::<String>()?) {
+            Ok(r) => r,
+            Err(why) => {
+                msg.channel_id
+                    .send_message(&ctx.http, |m| m.content(why))
+                    .await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let symbol = match iex::cache::universe() {
+        Some(universe) => match utils::resolve_listed(&universe, &symbol) {
+            Some(s) => s,
+            None => {
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        m.content(format!("Unknown symbol {}", symbol))
+                    })
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => {
+            error!("Could not get list of symbols");
+            symbol
+        }
+    };
+
+    let points = match client.chart(&symbol, range).await {
+        Ok(p) => p,
+        Err(why) => {
+            error!("Could not get chart for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+    let png = match chart::render_blocking(symbol.clone(), range, points).await
+    {
+        Ok(Some(png)) => png,
+        Ok(None) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("No chart data for {}", symbol))
+                })
+                .await?;
+            return Ok(());
+        }
+        Err(why) => {
+            error!("Could not render chart for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+    let filename = format!("{}-{}.png", symbol, range);
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} ({})", symbol, range));
+                e.attachment(&filename);
+                e
+            });
+            m.add_file((png.as_slice(), filename.as_str()));
+            m
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        arg,
+        result,
+        case::range("5D", Ok(ChartRange::FiveDays)),
+        case::unknown("2w", Err("Unknown chart range: `2w`")),
+        case::mention("@everyone", Err("Unknown chart range: `@everyone`")),
+        case::backtick("`@here`", Err("Unknown chart range: `'@here'`"))
+    )]
+    fn parse_range(arg: &str, result: Result<ChartRange, &str>) {
+        assert_eq!(super::parse_range(arg), result.map_err(str::to_string));
+    }
+}
@@ -0,0 +1,133 @@
+//
+
+use super::utils;
+
+use crate::iex::{Company, Stock};
+
+use num_format::{Locale, ToFormattedString};
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Longest description shown, IEX has some that run for paragraphs
+const MAX_DESCRIPTION: usize = 1024;
+
+/// The start of a description, cut at a word boundary if it is too long
+fn summarize(description: &str) -> String {
+    if description.chars().count() <= MAX_DESCRIPTION {
+        return description.to_string();
+    }
+
+    let cut: String = description.chars().take(MAX_DESCRIPTION).collect();
+    match cut.rfind(' ') {
+        Some(i) => format!("{}…", &cut[..i]),
+        None => format!("{}…", cut),
+    }
+}
+
+async fn send_company(
+    ctx: &Context,
+    msg: &Message,
+    company: &Company,
+    logo: Option<String>,
+) -> CommandResult {
+    let text = |value: &Option<String>| match value {
+        Some(v) if !v.is_empty() => v.clone(),
+        _ => "-".to_string(),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} ({})", company.name, company.symbol));
+                if let Some(website) = &company.website {
+                    if !website.is_empty() {
+                        e.url(website);
+                    }
+                }
+                if let Some(description) = &company.description {
+                    e.description(summarize(description));
+                }
+                if let Some(logo) = &logo {
+                    e.thumbnail(logo);
+                }
+
+                e.field("Industry", text(&company.industry), true);
+                e.field("Sector", text(&company.sector), true);
+                e.field("CEO", text(&company.ceo), true);
+                e.field(
+                    "Employees",
+                    company.employees.map_or_else(
+                        || "-".to_string(),
+                        |n| n.to_formatted_string(&Locale::en),
+                    ),
+                    true,
+                );
+                e.field("Country", text(&company.country), true);
+                e.field("Website", text(&company.website), true);
+                if !company.tags.is_empty() {
+                    e.field("Tags", company.tags.join(", "), false);
+                }
+                if let Some(exchange) = &company.exchange {
+                    e.footer(|f| f.text(exchange));
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[aliases(info)]
+#[usage("SYMBOL")]
+#[example("AAPL")]
+#[min_args(1)]
+async fn company(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let symbol = match utils::symbol_argument(ctx, msg, args.rest()).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (company, logo) =
+        tokio::join!(client.company(&symbol), client.logo(&symbol));
+
+    let company = match company {
+        Ok(c) => c,
+        Err(why) => {
+            error!("Could not get company for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let logo = match logo {
+        Ok(url) if !url.is_empty() => Some(url),
+        Ok(_) => None,
+        Err(why) => {
+            error!("Could not get logo for {}: {:?}", symbol, why);
+            None
+        }
+    };
+
+    send_company(ctx, msg, &company, logo).await
+}
@@ -0,0 +1,264 @@
+//
+
+use super::utils::{self, format_money};
+
+use crate::iex::{self, Dividend, Dividends};
+
+use chrono::{Duration, NaiveDate, Utc};
+use chrono_tz::America::New_York;
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// History asked of IEX, enough to cover the trailing year and more
+const DIVIDEND_RANGE: &str = "2y";
+
+/// Splits are rare, look further back for them
+const SPLIT_RANGE: &str = "5y";
+
+/// Most recent dividends shown
+const MAX_DIVIDENDS: usize = 8;
+
+/// Discord embeds hold at most 25 fields, one per split
+const MAX_SPLITS: usize = 25;
+
+fn date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Dividends with an ex-date in the year up to `today` as a percentage of
+/// `price`
+fn trailing_yield(
+    dividends: &[Dividend],
+    price: f64,
+    today: NaiveDate,
+) -> Option<f64> {
+    if price <= 0.0 {
+        return None;
+    }
+
+    let since = today - Duration::days(365);
+    let paid: f64 = dividends
+        .iter()
+        .filter(|d| {
+            matches!(date(&d.ex_date), Some(ex) if ex > since && ex <= today)
+        })
+        .map(|d| d.amount)
+        .sum();
+
+    Some(paid / price * 100.0)
+}
+
+/// Formats a split as the shares held after for those held before
+fn ratio(from: f64, to: f64) -> String {
+    format!("{}-for-{}", to, from)
+}
+
+#[command]
+#[usage("SYMBOL")]
+#[example("AAPL")]
+#[min_args(1)]
+async fn dividends(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let symbol = match utils::symbol_argument(ctx, msg, args.rest()).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut dividends = match client.dividends(&symbol, DIVIDEND_RANGE).await {
+        Ok(d) => d,
+        Err(why) => {
+            error!("Could not get dividends for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if dividends.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("No recent dividends for {}", symbol))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    // Dates are ISO formatted so sort as strings, newest first
+    dividends.sort_by(|a, b| b.ex_date.cmp(&a.ex_date));
+
+    let price = match iex::cache::quotes(&client, &[symbol.as_str()]).await {
+        Ok(quotes) => quotes.get(&symbol).map(|q| q.price),
+        Err(why) => {
+            error!("Could not get quote for {}: {:?}", symbol, why);
+            None
+        }
+    };
+
+    let today = Utc::now().with_timezone(&New_York).date().naive_local();
+    let yielded = price.and_then(|p| trailing_yield(&dividends, p, today));
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} dividends", symbol));
+                e.description(format!(
+                    "Trailing yield {}",
+                    yielded.map_or_else(
+                        || "-".to_string(),
+                        |y| format!("{:.2}%", y)
+                    )
+                ));
+                for dividend in dividends.iter().take(MAX_DIVIDENDS) {
+                    e.field(
+                        format!("Ex-date {}", dividend.ex_date),
+                        format!(
+                            "{} {}\nPaid {}",
+                            format_money(dividend.amount),
+                            dividend.frequency.as_deref().unwrap_or(""),
+                            dividend.payment_date.as_deref().unwrap_or("-")
+                        ),
+                        true,
+                    );
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[usage("SYMBOL")]
+#[example("TSLA")]
+#[min_args(1)]
+async fn splits(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let symbol = match utils::symbol_argument(ctx, msg, args.rest()).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut splits = match client.splits(&symbol, SPLIT_RANGE).await {
+        Ok(s) => s,
+        Err(why) => {
+            error!("Could not get splits for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if splits.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!(
+                    "No splits for {} in the last 5 years",
+                    symbol
+                ))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    splits.sort_by(|a, b| b.ex_date.cmp(&a.ex_date));
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} splits", symbol));
+                for split in splits.iter().take(MAX_SPLITS) {
+                    e.field(
+                        format!("Ex-date {}", split.ex_date),
+                        ratio(split.from_factor, split.to_factor),
+                        true,
+                    );
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn dividend(ex_date: &str, amount: f64) -> Dividend {
+        Dividend {
+            ex_date: ex_date.to_string(),
+            payment_date: None,
+            amount,
+            frequency: None,
+        }
+    }
+
+    #[rstest(
+        price,
+        expected,
+        case(100.0, Some(2.0)),
+        case(50.0, Some(4.0)),
+        case(0.0, None)
+    )]
+    fn trailing_yield(price: f64, expected: Option<f64>) {
+        let today = NaiveDate::from_ymd(2020, 8, 14);
+        let dividends = vec![
+            dividend("2020-08-07", 0.5),
+            dividend("2020-05-08", 0.5),
+            dividend("2020-02-07", 0.5),
+            dividend("2019-11-07", 0.5),
+            dividend("2019-08-09", 0.5),
+        ];
+
+        let result = super::trailing_yield(&dividends, price, today);
+        match (result, expected) {
+            (Some(r), Some(e)) => assert!((r - e).abs() < 1e-9),
+            (r, e) => assert_eq!(r, e),
+        }
+    }
+
+    #[rstest(
+        from,
+        to,
+        expected,
+        case(1.0, 4.0, "4-for-1"),
+        case(3.0, 1.0, "1-for-3")
+    )]
+    fn ratio(from: f64, to: f64, expected: &str) {
+        assert_eq!(super::ratio(from, to), expected);
+    }
+}
@@ -0,0 +1,372 @@
+//
+
+use super::utils::{self, format_money};
+
+use crate::iex::{self, Earnings, UpcomingEarnings};
+use crate::storage;
+use crate::utils::inline_code;
+
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::America::New_York;
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+use std::collections::BTreeMap;
+
+/// Quarters of results shown for a symbol
+const QUARTERS: usize = 4;
+
+/// Companies listed for each day of the week, the largest by market cap
+const MAX_PER_DAY: usize = 10;
+
+/// Most companies quoted to rank the week, a single batch request
+const MAX_CANDIDATES: usize = 100;
+
+fn eps(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), format_money)
+}
+
+/// The Monday and Friday of this week, or of next week at the weekend
+fn this_week(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let weekday = today.weekday();
+    let mut monday =
+        today - Duration::days(weekday.num_days_from_monday() as i64);
+    if let Weekday::Sat | Weekday::Sun = weekday {
+        monday += Duration::weeks(1);
+    }
+
+    (monday, monday + Duration::days(4))
+}
+
+/// Symbols on the watchlists where the message was sent, everyone's in a
+/// guild and the author's own in DMs
+async fn watched_symbols(ctx: &Context, msg: &Message) -> Option<Vec<String>> {
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            return None;
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let result = storage::blocking(db, move |db| match guild {
+        Some(guild) => db.watched_symbols(guild),
+        None => db.watchlist(None, user),
+    })
+    .await;
+
+    match result {
+        Ok(symbols) => Some(symbols),
+        Err(why) => {
+            error!("Could not load watchlists: {:?}", why);
+            None
+        }
+    }
+}
+
+/// Shares the quotes used for ranking between the days, each keeping its
+/// first companies in the order IEX listed them
+fn candidates(days: &mut BTreeMap<NaiveDate, Vec<String>>) {
+    let per_day = MAX_CANDIDATES / days.len().max(1);
+    for symbols in days.values_mut() {
+        symbols.truncate(per_day);
+    }
+}
+
+/// Keeps the largest companies reporting each day, by market cap
+async fn notable(
+    client: &iex::Client,
+    mut days: BTreeMap<NaiveDate, Vec<String>>,
+) -> BTreeMap<NaiveDate, Vec<String>> {
+    candidates(&mut days);
+
+    let symbols: Vec<&str> =
+        days.values().flatten().map(|s| s.as_str()).collect();
+
+    let caps = match iex::cache::quotes(client, &symbols).await {
+        Ok(quotes) => quotes,
+        Err(why) => {
+            error!("Could not get quotes for earnings: {:?}", why);
+            Default::default()
+        }
+    };
+    let cap = |symbol: &String| {
+        caps.get(symbol).and_then(|q| q.market_cap).unwrap_or(0.0)
+    };
+
+    days.into_iter()
+        .map(|(date, mut symbols)| {
+            symbols.sort_by(|a, b| {
+                cap(b)
+                    .partial_cmp(&cap(a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            symbols.truncate(MAX_PER_DAY);
+            (date, symbols)
+        })
+        .collect()
+}
+
+#[command]
+#[sub_commands(week)]
+#[usage("SYMBOL")]
+#[example("AAPL")]
+#[min_args(1)]
+async fn earnings(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let symbol = match utils::symbol_argument(ctx, msg, args.rest()).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let reports = match client.earnings(&symbol, QUARTERS).await {
+        Ok(r) => r,
+        Err(why) => {
+            error!("Could not get earnings for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if reports.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("No earnings reported for {}", symbol))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} earnings", symbol));
+                for report in &reports {
+                    e.field(
+                        format!(
+                            "{} ({})",
+                            report.fiscal_period.as_deref().unwrap_or("-"),
+                            report.report_date.as_deref().unwrap_or("-")
+                        ),
+                        format!(
+                            "Actual {} vs consensus {}\nSurprise {}",
+                            eps(report.actual_eps),
+                            eps(report.consensus_eps),
+                            report.surprise_percent().map_or_else(
+                                || "-".to_string(),
+                                |s| format!("{:+.2}%", s)
+                            )
+                        ),
+                        true,
+                    );
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[usage("[watchlist]")]
+#[example("watchlist")]
+#[max_args(1)]
+async fn week(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let watched = match args.single::<String>() {
+        Ok(a) if a.eq_ignore_ascii_case("watchlist") => {
+            match watched_symbols(ctx, msg).await {
+                Some(symbols) => Some(symbols),
+                None => {
+                    msg.channel_id
+                        .send_message(&ctx.http, |m| {
+                            m.content("Command Error")
+                        })
+                        .await?;
+                    return Ok(());
+                }
+            }
+        }
+        Ok(a) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!(
+                        "Unknown option {}, did you mean watchlist?",
+                        inline_code(&a)
+                    ))
+                })
+                .await?;
+            return Ok(());
+        }
+        Err(_) => None,
+    };
+
+    // Only needed to pick listings when not using the watchlists
+    let universe = iex::cache::universe();
+    if watched.is_none() && universe.is_none() {
+        error!("Could not get list of symbols");
+        msg.channel_id
+            .send_message(&ctx.http, |m| m.content("Command Error"))
+            .await?;
+        return Ok(());
+    }
+
+    let filter = utils::listing_filter(ctx, msg).await;
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let upcoming: Vec<UpcomingEarnings> =
+        match client.upcoming_earnings().await {
+            Ok(u) => u,
+            Err(why) => {
+                error!("Could not get upcoming earnings: {:?}", why);
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        m.content(format!("Earnings: {}", why.reason()))
+                    })
+                    .await?;
+                return Ok(());
+            }
+        };
+
+    let today = Utc::now().with_timezone(&New_York).date().naive_local();
+    let (monday, friday) = this_week(today);
+
+    let mut days: BTreeMap<NaiveDate, Vec<String>> = BTreeMap::new();
+    for report in upcoming {
+        let date =
+            match NaiveDate::parse_from_str(&report.report_date, "%Y-%m-%d") {
+                Ok(d) if (monday..=friday).contains(&d) => d,
+                _ => continue,
+            };
+
+        let include = match &watched {
+            Some(symbols) => symbols.contains(&report.symbol),
+            None => universe
+                .as_ref()
+                .and_then(|u| u.get(&report.symbol))
+                .map_or(false, |s| filter.allows(s)),
+        };
+
+        if include {
+            let symbols = days.entry(date).or_default();
+            if !symbols.contains(&report.symbol) {
+                symbols.push(report.symbol);
+            }
+        }
+    }
+
+    let days = match watched {
+        Some(_) => days,
+        None => notable(&client, days).await,
+    };
+
+    if days.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content("No earnings reports expected this week")
+            })
+            .await?;
+        return Ok(());
+    }
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!(
+                    "Earnings for the week of {}",
+                    monday.format("%b %-d")
+                ));
+                for (date, symbols) in &days {
+                    e.field(
+                        date.format("%A %b %-d"),
+                        symbols.join(", "),
+                        false,
+                    );
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[rstest(
+        today,
+        monday,
+        friday,
+        case::monday("2020-08-10", "2020-08-10", "2020-08-14"),
+        case::wednesday("2020-08-12", "2020-08-10", "2020-08-14"),
+        case::friday("2020-08-14", "2020-08-10", "2020-08-14"),
+        case::saturday("2020-08-15", "2020-08-17", "2020-08-21"),
+        case::sunday("2020-08-16", "2020-08-17", "2020-08-21"),
+        case::new_year("2021-01-02", "2021-01-04", "2021-01-08")
+    )]
+    fn this_week(today: &str, monday: &str, friday: &str) {
+        assert_eq!(
+            super::this_week(date(today)),
+            (date(monday), date(friday))
+        );
+    }
+
+    #[rstest(
+        days,
+        expected,
+        case::one_day(vec![150], vec![100]),
+        case::few(vec![10, 20], vec![10, 20]),
+        case::shared(vec![10, 80, 80], vec![10, 33, 33]),
+        case::none(vec![], vec![])
+    )]
+    fn candidates(days: Vec<usize>, expected: Vec<usize>) {
+        let mut days: BTreeMap<NaiveDate, Vec<String>> = days
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let symbols = (0..count).map(|n| format!("S{}", n)).collect();
+                (date("2020-08-10") + Duration::days(i as i64), symbols)
+            })
+            .collect();
+
+        super::candidates(&mut days);
+
+        let counts: Vec<usize> = days.values().map(Vec::len).collect();
+        assert_eq!(counts, expected);
+    }
+}
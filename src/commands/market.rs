@@ -4,7 +4,6 @@ use super::utils;
 
 use crate::iex::Market;
 use crate::iex::Quote;
-use crate::IEXClient;
 
 use serenity::{
     client::Context,
@@ -27,10 +26,9 @@ async fn market(
     msg: &Message,
     func: &MarketFunc,
 ) -> CommandResult {
-    let data = ctx.data.read().await;
     let mut quotes: Vec<Quote>;
 
-    if let Some(client) = data.get::<IEXClient>() {
+    if let Some(client) = utils::iex_client(ctx).await {
         match func {
             MarketFunc::Losers => {
                 quotes = client.losers().await?;
@@ -2,19 +2,64 @@
 
 use serenity::framework::{standard::macros::group, StandardFramework};
 
+mod alert;
+mod calendar;
+mod chart;
+mod company;
+mod dividends;
+mod earnings;
 mod market;
+mod news;
+mod portfolio;
 mod price;
+mod quote;
+mod search;
+mod settings;
+mod stats;
 mod utils;
+mod watch;
 
+use alert::ALERTS_COMMAND;
+use alert::ALERT_COMMAND;
+use calendar::MARKET_COMMAND;
+use chart::CHART_COMMAND;
+use company::COMPANY_COMMAND;
+use dividends::DIVIDENDS_COMMAND;
+use dividends::SPLITS_COMMAND;
+use earnings::EARNINGS_COMMAND;
 use market::GAINERS_COMMAND;
 use market::LOSERS_COMMAND;
 use market::MOVERS_COMMAND;
+use news::NEWS_COMMAND;
+use portfolio::BUY_COMMAND;
+use portfolio::LEADERBOARD_COMMAND;
+use portfolio::PORTFOLIO_COMMAND;
+use portfolio::SELL_COMMAND;
 use price::PRICE_COMMAND;
+use quote::QUOTE_COMMAND;
+use search::SEARCH_COMMAND;
+use settings::FILTERS_COMMAND;
+use stats::STATS_COMMAND;
+use watch::WATCHLIST_COMMAND;
+use watch::WATCH_COMMAND;
 
 #[group]
-#[commands(losers, gainers, movers, price)]
+#[commands(
+    market, losers, gainers, movers, price, quote, company, stats, earnings,
+    dividends, splits, news, search, chart, watch, watchlist, alert, alerts
+)]
 struct Stonks;
 
+#[group]
+#[commands(buy, sell, portfolio, leaderboard)]
+struct Trading;
+
+#[group]
+#[commands(filters)]
+struct Settings;
+
 pub fn configure_framework(f: StandardFramework) -> StandardFramework {
     f.group(&STONKS_GROUP)
+        .group(&TRADING_GROUP)
+        .group(&SETTINGS_GROUP)
 }
@@ -0,0 +1,270 @@
+//
+
+use super::utils::{self, format_ago};
+
+use crate::iex::{Article, News};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Headlines shown per stock unless asked for more
+const DEFAULT_HEADLINES: usize = 5;
+
+/// Most headlines shown per stock
+const MAX_HEADLINES: usize = 10;
+
+/// Discord embeds hold at most 25 fields, one per headline
+const MAX_FIELDS: usize = 25;
+
+/// Longest field name Discord accepts
+const MAX_HEADLINE: usize = 256;
+
+/// Longest field value Discord accepts
+const MAX_VALUE: usize = 1024;
+
+/// Longest source shown under a headline
+const MAX_SOURCE: usize = 64;
+
+/// Most characters Discord accepts across all of an embed's text
+const MAX_EMBED: usize = 6000;
+
+/// Kept free in the embed for the footer saying headlines were left out
+const FOOTER_ROOM: usize = 100;
+
+/// Cuts `text` to at most `max` characters, marking where it was cut
+fn shorten(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(max - 1).collect();
+    format!("{}…", cut)
+}
+
+/// Shortens a headline to fit in a field name
+fn headline(symbol: &str, article: &Article, prefix: bool) -> String {
+    if prefix {
+        shorten(&format!("{}: {}", symbol, article.headline), MAX_HEADLINE)
+    } else {
+        shorten(&article.headline, MAX_HEADLINE)
+    }
+}
+
+/// Where and when a headline was published, with a link to it unless the
+/// link would not fit in a field value
+fn byline(article: &Article, now: DateTime<Utc>) -> String {
+    let byline = format!(
+        "{} · {}",
+        shorten(&article.source, MAX_SOURCE),
+        format_ago(Utc.timestamp_millis(article.datetime), now)
+    );
+    let linked = format!("{} · [Read]({})", byline, article.url);
+
+    if linked.chars().count() <= MAX_VALUE {
+        linked
+    } else {
+        byline
+    }
+}
+
+/// The headlines as embed fields, as many as fit in a single embed, and
+/// how many had to be left out
+fn fields(
+    headlines: &[(String, Article)],
+    prefix: bool,
+    now: DateTime<Utc>,
+) -> (Vec<(String, String)>, usize) {
+    let mut fields = Vec::new();
+    let mut length = 0;
+
+    for (symbol, article) in headlines.iter().take(MAX_FIELDS) {
+        let name = headline(symbol, article, prefix);
+        let value = byline(article, now);
+
+        length += name.chars().count() + value.chars().count();
+        if length > MAX_EMBED - FOOTER_ROOM {
+            break;
+        }
+        fields.push((name, value));
+    }
+
+    let omitted = headlines.len() - fields.len();
+    (fields, omitted)
+}
+
+#[command]
+#[usage("[SYMBOL] [COUNT]")]
+#[example("AAPL")]
+#[example("TSLA 3")]
+async fn news(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut words: Vec<&str> = args.rest().split_whitespace().collect();
+
+    let count = match words.last().and_then(|w| w.parse::<usize>().ok()) {
+        Some(n) => {
+            words.pop();
+            n.clamp(1, MAX_HEADLINES)
+        }
+        None => DEFAULT_HEADLINES,
+    };
+
+    let stocks: Vec<String> = if words.is_empty() {
+        utils::last_stocks(ctx, msg).await
+    } else {
+        match utils::symbol_argument(ctx, msg, &words.join(" ")).await? {
+            Some(s) => vec![s],
+            None => return Ok(()),
+        }
+    };
+
+    if stocks.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| m.content("No symbols found"))
+            .await?;
+        return Ok(());
+    }
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut headlines: Vec<(String, Article)> = Vec::new();
+    let mut errors: Vec<(String, String)> = Vec::new();
+
+    for stock in &stocks {
+        // Ask for extra as paywalled articles are left out
+        match client.news(stock, count * 2).await {
+            Ok(articles) => headlines.extend(
+                articles
+                    .into_iter()
+                    .filter(|a| !a.has_paywall)
+                    .take(count)
+                    .map(|a| (stock.clone(), a)),
+            ),
+            Err(why) => {
+                error!("Could not get news for {}: {:?}", stock, why);
+                errors.push((stock.clone(), why.reason().to_string()));
+            }
+        }
+    }
+
+    if headlines.is_empty() && errors.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("No news for {}", stocks.join(", ")))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    if !headlines.is_empty() {
+        let (fields, omitted) =
+            fields(&headlines, stocks.len() > 1, Utc::now());
+
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| {
+                    for (name, value) in fields {
+                        e.field(name, value, false);
+                    }
+                    if omitted > 0 {
+                        e.footer(|f| {
+                            f.text(format!(
+                                "{} more headlines left out to fit in one \
+                                 message",
+                                omitted
+                            ))
+                        });
+                    }
+                    e
+                })
+            })
+            .await?;
+    }
+
+    utils::send_quotes(ctx, msg, Vec::new(), errors).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn article(headline_length: usize, url_length: usize) -> Article {
+        Article {
+            datetime: 1_597_435_200_000,
+            headline: "a".repeat(headline_length),
+            source: "Reuters".to_string(),
+            url: format!("https://example.com/{}", "b".repeat(url_length)),
+            has_paywall: false,
+        }
+    }
+
+    #[rstest(
+        count,
+        headline_length,
+        url_length,
+        shown,
+        linked,
+        case::short(10, 80, 40, 10, true),
+        case::too_many(30, 20, 20, MAX_FIELDS, true),
+        case::too_long(10, 300, 600, 6, true),
+        case::long_url(10, 300, 5000, 10, false)
+    )]
+    fn fields(
+        count: usize,
+        headline_length: usize,
+        url_length: usize,
+        shown: usize,
+        linked: bool,
+    ) {
+        let headlines: Vec<(String, Article)> = (0..count)
+            .map(|_| {
+                ("AAPL".to_string(), article(headline_length, url_length))
+            })
+            .collect();
+        let now = Utc.timestamp_millis(1_597_438_800_000);
+
+        let (fields, omitted) = super::fields(&headlines, true, now);
+        let length: usize = fields
+            .iter()
+            .map(|(n, v)| n.chars().count() + v.chars().count())
+            .sum();
+
+        assert_eq!(fields.len(), shown);
+        assert_eq!(omitted, count - shown);
+        assert!(length <= MAX_EMBED - FOOTER_ROOM);
+        assert!(fields
+            .iter()
+            .all(|(n, _)| n.chars().count() <= MAX_HEADLINE));
+        assert!(fields.iter().all(|(_, v)| v.chars().count() <= MAX_VALUE));
+        assert!(fields.iter().all(|(_, v)| v.contains("[Read]") == linked));
+    }
+
+    #[test]
+    fn long_source() {
+        let mut article = article(20, 20);
+        article.source = "s".repeat(2000);
+        let now = Utc.timestamp_millis(1_597_438_800_000);
+
+        let value = byline(&article, now);
+
+        assert!(value.chars().count() <= MAX_VALUE);
+        assert!(value.contains("[Read]"));
+    }
+}
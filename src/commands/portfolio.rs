@@ -0,0 +1,380 @@
+//
+
+use super::utils::{self, format_money};
+
+use crate::iex;
+use crate::iex::{Quote, Stock};
+use crate::portfolio::Portfolio;
+use crate::storage;
+use crate::utils::normalize_symbol;
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+use std::collections::HashMap;
+
+/// Number of users shown on the leaderboard
+const LEADERBOARD_SIZE: usize = 10;
+
+/// Discord embeds hold at most 25 fields, one per position
+const MAX_FIELDS: usize = 25;
+
+enum Side {
+    Buy,
+    Sell,
+}
+
+async fn current_quotes(
+    ctx: &Context,
+    symbols: Vec<String>,
+) -> Option<HashMap<String, Quote>> {
+    if symbols.is_empty() {
+        return Some(HashMap::new());
+    }
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            return None;
+        }
+    };
+
+    let request: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+    match iex::cache::quotes(&client, &request).await {
+        Ok(q) => Some(q),
+        Err(why) => {
+            error!("Could not get quotes for portfolio: {:?}", why);
+            None
+        }
+    }
+}
+
+async fn trade(
+    ctx: &Context,
+    msg: &Message,
+    mut args: Args,
+    side: Side,
+) -> CommandResult {
+    let shares = match args.single::<u64>() {
+        Ok(s) => s,
+        Err(_) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content("Give a whole number of shares, e.g. `10 AAPL`")
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+    let symbol = normalize_symbol(&args.single::<String>()?);
+
+    let symbol = match iex::cache::universe() {
+        Some(universe) => match utils::resolve_listed(&universe, &symbol) {
+            Some(s) => s,
+            None => {
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        m.content(format!("{}: {}", utils::UNKNOWN, symbol))
+                    })
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => {
+            error!("Could not get list of symbols");
+            symbol
+        }
+    };
+
+    let quote = {
+        let client = match utils::iex_client(ctx).await {
+            Some(c) => c,
+            None => {
+                error!("Could not get iex client");
+                msg.channel_id
+                    .send_message(&ctx.http, |m| m.content("Command Error"))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        match client.quote(&symbol).await {
+            Ok(q) => q,
+            Err(why) => {
+                error!("Could not get quote for {}: {:?}", symbol, why);
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        m.content(format!("{}: {}", symbol, why.reason()))
+                    })
+                    .await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let (traded, price) = (symbol.clone(), quote.price);
+    let result = storage::blocking(db, move |db| {
+        db.update_portfolio(guild, user, |portfolio| {
+            let result = match side {
+                Side::Buy => {
+                    portfolio.buy(&traded, shares, price).map(|_| None)
+                }
+                Side::Sell => portfolio.sell(&traded, shares, price).map(Some),
+            };
+            result.map(|realized| (realized, portfolio.cash))
+        })
+    })
+    .await?;
+
+    let content = match result {
+        Ok((realized, cash)) => {
+            let mut content = format!(
+                "{} {} {} at {} for {}",
+                if realized.is_some() { "Sold" } else { "Bought" },
+                shares,
+                symbol,
+                format_money(quote.price),
+                format_money(shares as f64 * quote.price),
+            );
+            if let Some(realized) = realized {
+                content.push_str(&format!(
+                    ", realizing {}",
+                    format_money(realized)
+                ));
+            }
+            content.push_str(&format!("\nCash: {}", format_money(cash)));
+            content
+        }
+        Err(why) => why.to_string(),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[usage("SHARES SYMBOL")]
+#[example("10 AAPL")]
+#[num_args(2)]
+async fn buy(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    trade(ctx, msg, args, Side::Buy).await
+}
+
+#[command]
+#[usage("SHARES SYMBOL")]
+#[example("5 AAPL")]
+#[num_args(2)]
+async fn sell(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    trade(ctx, msg, args, Side::Sell).await
+}
+
+#[command]
+#[aliases(pf)]
+async fn portfolio(
+    ctx: &Context,
+    msg: &Message,
+    _args: Args,
+) -> CommandResult {
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let portfolio: Portfolio =
+        storage::blocking(db, move |db| db.portfolio(guild, user)).await?;
+
+    let quotes = match current_quotes(
+        ctx,
+        portfolio.positions.keys().cloned().collect(),
+    )
+    .await
+    {
+        Some(q) => q,
+        None => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+    let prices: HashMap<String, f64> =
+        quotes.iter().map(|(s, q)| (s.clone(), q.price)).collect();
+
+    let value = portfolio.value(&prices);
+    let total_return = portfolio.total_return(&prices);
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{}'s portfolio", msg.author.name));
+                e.description(format!(
+                    "Cash: {}\nValue: {}\nReturn: {:+.2}%",
+                    format_money(portfolio.cash),
+                    format_money(value),
+                    total_return * 100.0
+                ));
+
+                for (symbol, position) in
+                    portfolio.positions.iter().take(MAX_FIELDS)
+                {
+                    let shares = position.shares as f64;
+                    let average = position.cost / shares;
+
+                    let detail = match quotes.get(symbol) {
+                        Some(q) => {
+                            let current = shares * q.price;
+                            let pnl = current - position.cost;
+                            format!(
+                                "{} @ {}\nCost {}\nValue {}\n\
+                                 Day change on holdings {}\n\
+                                 P&L {} ({:+.2}%)",
+                                position.shares,
+                                format_money(average),
+                                format_money(position.cost),
+                                format_money(current),
+                                format_money(shares * q.change),
+                                format_money(pnl),
+                                pnl / position.cost * 100.0
+                            )
+                        }
+                        None => format!(
+                            "{} @ {}\nCost {}\nNo current quote",
+                            position.shares,
+                            format_money(average),
+                            format_money(position.cost)
+                        ),
+                    };
+
+                    e.field(format!("**{}**", symbol), detail, true);
+                }
+
+                let hidden =
+                    portfolio.positions.len().saturating_sub(MAX_FIELDS);
+                if hidden > 0 {
+                    e.footer(|f| {
+                        f.text(format!(
+                            "and {} more positions not shown",
+                            hidden
+                        ))
+                    });
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+async fn leaderboard(
+    ctx: &Context,
+    msg: &Message,
+    _args: Args,
+) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let portfolios: Vec<Portfolio> =
+        storage::blocking(db, move |db| db.guild_portfolios(guild)).await?;
+
+    if portfolios.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content("Nobody is trading yet, start with `!buy 10 AAPL`")
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let mut symbols: Vec<String> = portfolios
+        .iter()
+        .flat_map(|p| p.positions.keys().cloned())
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+
+    let prices: HashMap<String, f64> = match current_quotes(ctx, symbols).await
+    {
+        Some(q) => q.into_iter().map(|(s, q)| (s, q.price)).collect(),
+        None => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut ranked: Vec<(&Portfolio, f64)> = portfolios
+        .iter()
+        .map(|p| (p, p.total_return(&prices)))
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let lines: Vec<String> = ranked
+        .iter()
+        .take(LEADERBOARD_SIZE)
+        .enumerate()
+        .map(|(i, (p, r))| {
+            format!(
+                "{}. <@{}> {:+.2}% ({})",
+                i + 1,
+                p.user.0,
+                r * 100.0,
+                format_money(p.value(&prices))
+            )
+        })
+        .collect();
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title("Leaderboard");
+                e.description(lines.join("\n"));
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//
+
+use super::utils::{self, format_compact, format_money, format_time};
+
+use crate::iex::Quote;
+use crate::utils::normalize_symbol;
+
+use chrono::Utc;
+use num_format::{Locale, ToFormattedString};
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Formats an optional value, IEX leaves fields it does not have null
+fn or_unknown<T, F>(value: Option<T>, f: F) -> String
+where
+    F: FnOnce(T) -> String,
+{
+    value.map_or_else(|| "-".to_string(), f)
+}
+
+async fn send_quote(
+    ctx: &Context,
+    msg: &Message,
+    quote: Quote,
+) -> CommandResult {
+    let now = Utc::now();
+
+    let range = |low: Option<f64>, high: Option<f64>| match (low, high) {
+        (Some(low), Some(high)) => {
+            format!("{} - {}", format_money(low), format_money(high))
+        }
+        _ => "-".to_string(),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} ({})", quote.name, quote.symbol));
+                e.description(format!(
+                    "**{}** {:+.2} ({:+.2}%)\n`{}` as of {}{}",
+                    format_money(quote.price),
+                    quote.change,
+                    quote.change_percent * 100.0,
+                    utils::quote_session(&quote),
+                    format_time(utils::quote_time(&quote), now),
+                    utils::extended_hours(&quote, now)
+                        .map_or_else(String::new, |x| format!("\n{}", x))
+                ));
+
+                e.field(
+                    "Previous close",
+                    or_unknown(quote.previous_close, format_money),
+                    true,
+                );
+                e.field("Open", or_unknown(quote.open, format_money), true);
+                e.field("Day range", range(quote.low, quote.high), true);
+                e.field(
+                    "52-week range",
+                    range(quote.week52_low, quote.week52_high),
+                    true,
+                );
+                e.field(
+                    "Volume",
+                    or_unknown(quote.volume, |v| {
+                        v.to_formatted_string(&Locale::en)
+                    }),
+                    true,
+                );
+                e.field(
+                    "Average volume",
+                    or_unknown(quote.avg_total_volume, |v| {
+                        v.to_formatted_string(&Locale::en)
+                    }),
+                    true,
+                );
+                e.field(
+                    "Market cap",
+                    or_unknown(quote.market_cap, format_compact),
+                    true,
+                );
+                e.field(
+                    "P/E",
+                    or_unknown(quote.pe_ratio, |pe| format!("{:.2}", pe)),
+                    true,
+                );
+                e.field(
+                    "YTD change",
+                    or_unknown(quote.ytd_change, |c| {
+                        format!("{:+.2}%", c * 100.0)
+                    }),
+                    true,
+                );
+                if let Some(exchange) = &quote.primary_exchange {
+                    e.footer(|f| f.text(exchange));
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[usage("SYMBOL")]
+#[example("AAPL")]
+#[example("bank of america")]
+#[min_args(1)]
+async fn quote(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query = args.rest().trim().trim_matches('"');
+    let stock = if query.contains(char::is_whitespace) {
+        query.to_string()
+    } else {
+        normalize_symbol(query)
+    };
+
+    let filter = utils::listing_filter(ctx, msg).await;
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match utils::fetch_quotes(&client, &filter, vec![stock]).await {
+        Some((mut quotes, errors)) => match quotes.pop() {
+            Some(quote) => send_quote(ctx, msg, quote).await?,
+            None => utils::send_quotes(ctx, msg, Vec::new(), errors).await?,
+        },
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
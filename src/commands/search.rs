@@ -0,0 +1,68 @@
+//
+
+use crate::iex;
+use crate::utils::inline_code;
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Most listings shown for a search
+const MAX_RESULTS: usize = 10;
+
+#[command]
+#[usage("TEXT")]
+#[example("apple")]
+#[example("brk")]
+#[min_args(1)]
+async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query = args.rest().trim().to_string();
+
+    let universe = match iex::cache::universe() {
+        Some(u) => u,
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let results = universe.names().search(&query, MAX_RESULTS);
+    if results.is_empty() {
+        msg.channel_id
+            .send_message(&ctx.http, |m| {
+                m.content(format!("No listings match {}", inline_code(&query)))
+            })
+            .await?;
+        return Ok(());
+    }
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("Listings matching {}", query));
+                for symbol in results {
+                    e.field(
+                        format!("**{}**", symbol.symbol),
+                        format!(
+                            "*{}*\n{} · {} · {}",
+                            symbol.name,
+                            symbol.exchange,
+                            symbol.issue_type_name(),
+                            symbol.currency
+                        ),
+                        true,
+                    );
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//
+
+use super::utils;
+
+use crate::filter::ListingFilter;
+use crate::iex;
+use crate::storage;
+use crate::utils::inline_code;
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::error;
+
+/// Applies `f` to the guild's listing filter and saves the result,
+/// replying with the filter now in effect
+async fn update<F>(ctx: &Context, msg: &Message, f: F) -> CommandResult
+where
+    F: FnOnce(&mut ListingFilter) + Send + 'static,
+{
+    let guild = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let filter = storage::blocking(db, move |db| {
+        let mut filter = db.listing_filter(Some(guild))?;
+        f(&mut filter);
+        db.set_listing_filter(guild, &filter)?;
+        Ok(filter)
+    })
+    .await?;
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(filter))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[sub_commands(types, exchanges, reset)]
+#[only_in(guilds)]
+async fn filters(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let guild = msg.guild_id;
+    let filter =
+        storage::blocking(db, move |db| db.listing_filter(guild)).await?;
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(filter))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[usage("TYPE [TYPE ...]")]
+#[example("cs et ad")]
+#[min_args(1)]
+async fn types(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut types: Vec<String> = Vec::new();
+    for arg in args.iter::<String>().filter_map(Result::ok) {
+        let code = arg.to_lowercase();
+        if !iex::ISSUE_TYPES.iter().any(|(c, _)| *c == code) {
+            let known: Vec<&str> =
+                iex::ISSUE_TYPES.iter().map(|(c, _)| *c).collect();
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!(
+                        "Unknown issue type {}, expected one of {}",
+                        inline_code(&arg),
+                        known.join(", ")
+                    ))
+                })
+                .await?;
+            return Ok(());
+        }
+
+        if !types.contains(&code) {
+            types.push(code);
+        }
+    }
+
+    update(ctx, msg, |f| f.issue_types = types).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[usage("any|EXCHANGE [EXCHANGE ...]")]
+#[example("NYS NAS")]
+#[min_args(1)]
+async fn exchanges(
+    ctx: &Context,
+    msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    let universe = match iex::cache::universe() {
+        Some(u) => u,
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut exchanges: Vec<String> = Vec::new();
+    for arg in args.iter::<String>().filter_map(Result::ok) {
+        if arg.eq_ignore_ascii_case("any") {
+            exchanges.clear();
+            break;
+        }
+
+        let exchange = arg.to_uppercase();
+        if !universe.exchanges().contains(&exchange) {
+            let known: Vec<&str> =
+                universe.exchanges().iter().map(|e| e.as_str()).collect();
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!(
+                        "Unknown exchange {}, expected one of {}",
+                        inline_code(&arg),
+                        known.join(", ")
+                    ))
+                })
+                .await?;
+            return Ok(());
+        }
+
+        if !exchanges.contains(&exchange) {
+            exchanges.push(exchange);
+        }
+    }
+
+    update(ctx, msg, |f| f.exchanges = exchanges).await
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+async fn reset(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(g) => g,
+        None => return Ok(()),
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    storage::blocking(db, move |db| db.reset_listing_filter(guild)).await?;
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(ListingFilter::default()))
+        .await?;
+    Ok(())
+}
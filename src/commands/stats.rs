@@ -0,0 +1,163 @@
+//
+
+use super::utils::{self, format_compact, format_money};
+
+use crate::iex::{AdvancedStats, KeyStats, Stock};
+
+use num_format::{Locale, ToFormattedString};
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use log::{error, info};
+
+/// Formats a whole number with thousands separators
+fn count(value: f64) -> String {
+    (value.round() as i64).to_formatted_string(&Locale::en)
+}
+
+fn ratio(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.2}%", value * 100.0)
+}
+
+/// Formats an optional figure, IEX leaves figures it does not have null
+fn figure(value: Option<f64>, f: fn(f64) -> String) -> String {
+    value.map_or_else(|| "-".to_string(), f)
+}
+
+async fn send_stats(
+    ctx: &Context,
+    msg: &Message,
+    symbol: &str,
+    stats: &KeyStats,
+    advanced: Option<&AdvancedStats>,
+) -> CommandResult {
+    let earnings = match &stats.next_earnings_date {
+        Some(date) if !date.is_empty() && date != "0" => date.clone(),
+        _ => "-".to_string(),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{} ({})", stats.company_name, symbol));
+
+                e.field(
+                    "Market cap",
+                    figure(stats.market_cap, format_compact),
+                    true,
+                );
+                e.field(
+                    "Shares outstanding",
+                    figure(stats.shares_outstanding, count),
+                    true,
+                );
+                e.field("Float", figure(stats.float, count), true);
+                e.field("Beta", figure(stats.beta, ratio), true);
+                e.field(
+                    "Dividend yield",
+                    figure(stats.dividend_yield, percent),
+                    true,
+                );
+                e.field(
+                    "EPS (TTM)",
+                    figure(stats.ttm_eps, format_money),
+                    true,
+                );
+                e.field("P/E", figure(stats.pe_ratio, ratio), true);
+
+                if let Some(a) = advanced {
+                    e.field(
+                        "Forward P/E",
+                        figure(a.forward_pe_ratio, ratio),
+                        true,
+                    );
+                    e.field("PEG", figure(a.peg_ratio, ratio), true);
+                    e.field(
+                        "Revenue",
+                        figure(a.revenue, format_compact),
+                        true,
+                    );
+                    e.field(
+                        "Profit margin",
+                        figure(a.profit_margin, percent),
+                        true,
+                    );
+                    e.field(
+                        "Debt/equity",
+                        figure(a.debt_to_equity, ratio),
+                        true,
+                    );
+                }
+
+                e.field("Next earnings", earnings, true);
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[usage("SYMBOL")]
+#[example("AAPL")]
+#[min_args(1)]
+async fn stats(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let symbol = match utils::symbol_argument(ctx, msg, args.rest()).await? {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Advanced stats are not available on every IEX plan, fall back to
+    // the key stats they extend
+    let advanced = match client.advanced_stats(&symbol).await {
+        Ok(a) => Some(a),
+        Err(why) if why.is_entitlement() => {
+            info!("Advanced stats unavailable for {}: {}", symbol, why);
+            None
+        }
+        Err(why) => {
+            error!("Could not get advanced stats for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(a) = &advanced {
+        return send_stats(ctx, msg, &symbol, &a.stats, Some(a)).await;
+    }
+
+    match client.stats(&symbol).await {
+        Ok(stats) => send_stats(ctx, msg, &symbol, &stats, None).await,
+        Err(why) => {
+            error!("Could not get stats for {}: {:?}", symbol, why);
+            msg.channel_id
+                .send_message(&ctx.http, |m| {
+                    m.content(format!("{}: {}", symbol, why.reason()))
+                })
+                .await?;
+            Ok(())
+        }
+    }
+}
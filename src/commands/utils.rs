@@ -1,18 +1,374 @@
 //
 
-use crate::iex::Quote;
+use crate::calendar::{self, Session};
+use crate::filter::ListingFilter;
+use crate::iex;
+use crate::iex::{Quote, Symbol, Universe};
+use crate::storage::{self, Storage};
+use crate::utils::{inline_code, normalize_symbol, resolve_symbol};
+use crate::{Database, IEXClient};
 
 use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use itertools::Itertools;
 use num_format::{Locale, ToFormattedString};
-use serenity::{client::Context, model::channel::Message};
+use serenity::{
+    client::Context, framework::standard::Args, model::channel::Message,
+};
+
+use log::error;
+use std::sync::Arc;
+
+pub const UNKNOWN: &str = "Unknown symbol";
+
+/// Formats a dollar amount with thousands separators, e.g. `-$1,234.50`
+pub fn format_money(value: f64) -> String {
+    let cents = (value.abs() * 100.0).round() as i64;
+    format!(
+        "{}${}.{:02}",
+        if value < 0.0 && cents > 0 { "-" } else { "" },
+        (cents / 100).to_formatted_string(&Locale::en),
+        cents % 100
+    )
+}
+
+/// Formats a large amount with a magnitude suffix, e.g. `$2.13T`
+pub fn format_compact(value: f64) -> String {
+    let magnitude = value.abs();
+    let (value, suffix) = if magnitude >= 1e12 {
+        (value / 1e12, "T")
+    } else if magnitude >= 1e9 {
+        (value / 1e9, "B")
+    } else if magnitude >= 1e6 {
+        (value / 1e6, "M")
+    } else {
+        return format_money(value);
+    };
+
+    format!(
+        "{}${:.2}{}",
+        if value < 0.0 { "-" } else { "" },
+        value.abs(),
+        suffix
+    )
+}
+
+/// Formats a time in New York, with the date unless it is the same day
+/// as `now`, e.g. `Fri Nov 27 1:00 PM EST`
+pub fn format_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let local = time.with_timezone(&New_York);
+
+    if local.date() == now.with_timezone(&New_York).date() {
+        local.format("%-I:%M %p %Z").to_string()
+    } else {
+        local.format("%a %b %-d %-I:%M %p %Z").to_string()
+    }
+}
+
+/// Formats how long before `now` a time was, e.g. `3h ago`
+pub fn format_ago(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - time).num_minutes();
+
+    if minutes < 1 {
+        "just now".to_string()
+    } else if minutes < 60 {
+        format!("{}m ago", minutes)
+    } else if minutes < 60 * 24 {
+        format!("{}h ago", minutes / 60)
+    } else {
+        format!("{}d ago", minutes / (60 * 24))
+    }
+}
+
+/// Where a quote's price comes from, the session it was traded in unless
+/// it is a closing price
+pub fn quote_session(quote: &Quote) -> String {
+    match quote.calculation_price.as_str() {
+        "close" => "Close".to_string(),
+        "previousclose" => "Previous close".to_string(),
+        _ => calendar::session(quote_time(quote)).to_string(),
+    }
+}
+
+/// When a quote's price was last updated
+pub fn quote_time(quote: &Quote) -> DateTime<Utc> {
+    Utc.timestamp_millis(quote.time as i64)
+}
+
+/// The pre or post market move of a quote, if it is more recent than the
+/// regular session
+pub fn extended_hours(quote: &Quote, now: DateTime<Utc>) -> Option<String> {
+    let price = quote.extended_price?;
+    let time = quote.extended_price_time?;
+
+    if time <= quote.time as i64 || time <= quote.close_time.unwrap_or(0) {
+        return None;
+    }
+
+    let time = Utc.timestamp_millis(time);
+    let session = match calendar::session(time) {
+        s @ Session::PreMarket | s @ Session::AfterHours => s.to_string(),
+        _ => "Extended hours".to_string(),
+    };
+
+    Some(format!(
+        "`{}` {} {:+.2} ({:+.2}%) as of {}",
+        session,
+        format_money(price),
+        quote.extended_change.unwrap_or(0.0),
+        quote.extended_change_percent.unwrap_or(0.0) * 100.0,
+        format_time(time, now)
+    ))
+}
+
+/// Symbols given as command arguments, normalized and deduplicated
+///
+/// Quoted arguments containing spaces can only be company names, so are
+/// kept as given.
+pub fn parse_symbols(args: &mut Args) -> Vec<String> {
+    args.trimmed()
+        .quoted()
+        .iter::<String>()
+        .filter_map(|x| match x {
+            Ok(a) if a.contains(char::is_whitespace) => Some(a),
+            Ok(a) => Some(normalize_symbol(&a)),
+            Err(_) => None,
+        })
+        .filter(|s| !s.is_empty())
+        .unique()
+        .collect()
+}
+
+/// The listed form of a normalized symbol, e.g. `BRK.B`
+pub fn resolve_listed(universe: &Universe, symbol: &str) -> Option<String> {
+    resolve_symbol(symbol, |s| universe.contains(s))
+}
+
+/// Splits normalized symbols into the listed forms of those that are
+/// known and those that are not
+pub fn partition_listed(
+    universe: &Universe,
+    stocks: Vec<String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut known = Vec::new();
+    let mut unknown = Vec::new();
+
+    for stock in stocks {
+        match resolve_listed(universe, &stock) {
+            Some(s) if !known.contains(&s) => known.push(s),
+            Some(_) => {}
+            None => unknown.push(stock),
+        }
+    }
+
+    (known, unknown)
+}
+
+/// The listed forms of normalized symbols, as `partition_listed` gives
+/// them, keeping those that are not listed as given
+pub fn listed_forms(universe: &Universe, stocks: Vec<String>) -> Vec<String> {
+    stocks
+        .into_iter()
+        .map(|s| resolve_listed(universe, &s).unwrap_or(s))
+        .unique()
+        .collect()
+}
+
+/// A copy of the IEX client, so the shared data is not locked while
+/// requests are made
+pub async fn iex_client(ctx: &Context) -> Option<iex::Client> {
+    ctx.data.read().await.get::<IEXClient>().cloned()
+}
+
+/// The shared storage, cloned out so the data lock is not held while it
+/// is used
+pub async fn database(ctx: &Context) -> Option<Arc<Storage>> {
+    ctx.data.read().await.get::<Database>().cloned()
+}
+
+/// Stocks most recently mentioned in the channel, empty if they cannot
+/// be loaded
+pub async fn last_stocks(ctx: &Context, msg: &Message) -> Vec<String> {
+    let db = match database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            return Vec::new();
+        }
+    };
+
+    let channel = msg.channel_id;
+    storage::blocking(db, move |db| db.last_stocks(channel))
+        .await
+        .unwrap_or_else(|why| {
+            error!("Could not load last stocks: {:?}", why);
+            Vec::new()
+        })
+}
+
+/// The listings eligible where the message was sent
+pub async fn listing_filter(ctx: &Context, msg: &Message) -> ListingFilter {
+    let db = match database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            return ListingFilter::default();
+        }
+    };
+
+    let guild = msg.guild_id;
+    let result =
+        storage::blocking(db, move |db| db.listing_filter(guild)).await;
+
+    result.unwrap_or_else(|why| {
+        error!("Could not load listing filter: {:?}", why);
+        ListingFilter::default()
+    })
+}
+
+/// Asks the user to pick between issues matching a company name
+pub fn did_you_mean(matches: &[&Symbol]) -> String {
+    format!(
+        "Did you mean {}?",
+        matches
+            .iter()
+            .map(|m| format!("{} ({})", m.symbol, m.name))
+            .join(", ")
+    )
+}
+
+/// The listed symbol for a command argument, which is looked up as a
+/// company name if it is not a symbol
+///
+/// Errors with the reply to send when nothing or several listings match.
+pub fn resolve_stock(
+    universe: &Universe,
+    stock: &str,
+) -> std::result::Result<String, String> {
+    if let Some(symbol) = resolve_listed(universe, &normalize_symbol(stock)) {
+        return Ok(symbol);
+    }
+
+    match universe.names().find(stock).as_slice() {
+        [] => Err(format!("{}: {}", UNKNOWN, inline_code(stock))),
+        [symbol] => Ok(symbol.symbol.clone()),
+        matches => Err(did_you_mean(matches)),
+    }
+}
+
+/// The symbol or company named by a command's arguments, replying
+/// and returning `None` if it is not listed
+///
+/// Without the list of symbols the argument is used as given.
+pub async fn symbol_argument(
+    ctx: &Context,
+    msg: &Message,
+    query: &str,
+) -> Result<Option<String>> {
+    let query = query.trim().trim_matches('"');
+
+    let universe = match iex::cache::universe() {
+        Some(u) => u,
+        None => {
+            error!("Could not get list of symbols");
+            return Ok(Some(normalize_symbol(query)));
+        }
+    };
+
+    match resolve_stock(&universe, query) {
+        Ok(symbol) => Ok(Some(symbol)),
+        Err(reply) => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content(reply))
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Fetches quotes for the symbols in a single batch, pairing each symbol
+/// that could not be quoted with the reason why
+///
+/// Anything that is not a symbol is looked up as a company name. Listings
+/// the `filter` does not allow are not quoted.
+///
+/// Returns `None` if the list of known symbols is unavailable.
+pub async fn fetch_quotes(
+    client: &iex::Client,
+    filter: &ListingFilter,
+    stocks: Vec<String>,
+) -> Option<(Vec<Quote>, Vec<(String, String)>)> {
+    let universe = iex::cache::universe()?;
+
+    let mut quotes: Vec<Quote> = Vec::new();
+    let mut errors: Vec<(String, String)> = Vec::new();
+
+    let (listed, unknown) = partition_listed(&universe, stocks);
+    let mut known = Vec::new();
+    for stock in listed {
+        match universe.get(&stock).and_then(|s| filter.reason(s)) {
+            Some(reason) => errors.push((stock, reason)),
+            None => known.push(stock),
+        }
+    }
+
+    for stock in unknown {
+        let matches: Vec<&Symbol> = universe
+            .names()
+            .find(&stock)
+            .into_iter()
+            .filter(|s| filter.allows(s))
+            .collect();
+
+        match matches.as_slice() {
+            [] => errors.push((stock, UNKNOWN.to_string())),
+            [m] => {
+                if !known.contains(&m.symbol) {
+                    known.push(m.symbol.clone());
+                }
+            }
+            _ => errors.push((stock, did_you_mean(&matches))),
+        }
+    }
+
+    if !known.is_empty() {
+        let request: Vec<&str> = known.iter().map(|s| s.as_str()).collect();
+
+        match iex::cache::quotes(client, &request).await {
+            Ok(mut found) => {
+                for stock in known {
+                    match found.remove(&stock) {
+                        Some(q) => quotes.push(q),
+                        None => errors.push((stock, UNKNOWN.to_string())),
+                    }
+                }
+            }
+            Err(why) => {
+                error!(
+                    "Could not get quotes for {}: {:?}",
+                    request.join(", "),
+                    why
+                );
+                errors.extend(
+                    known.into_iter().map(|s| (s, why.reason().to_string())),
+                );
+            }
+        }
+    }
+
+    Some((quotes, errors))
+}
 
 pub async fn send_quotes(
     ctx: &Context,
     msg: &Message,
     quotes: Vec<Quote>,
-    errors: Vec<String>,
+    errors: Vec<(String, String)>,
 ) -> Result<()> {
     if !quotes.is_empty() {
+        let now = Utc::now();
+
         msg.channel_id
             .send_message(&ctx.http, |m| {
                 m.embed(|e| {
@@ -44,14 +400,30 @@ pub async fn send_quotes(
                         let price = format!(":dollar: {}", quote.price);
                         let volume = format!(
                             ":loudspeaker: {}",
-                            quote.volume.to_formatted_string(&Locale::en)
+                            quote
+                                .volume
+                                .unwrap_or(0)
+                                .to_formatted_string(&Locale::en)
+                        );
+                        let mut session = format!(
+                            "`{}` as of {}",
+                            quote_session(&quote),
+                            format_time(quote_time(&quote), now)
                         );
+                        if let Some(extended) = extended_hours(&quote, now) {
+                            session = format!("{}\n{}", session, extended);
+                        }
 
                         e.field(
                             format!("**{}**", quote.symbol),
                             format!(
-                                "*{}*\n{}  {}\n{}  {}",
-                                quote.name, price, volume, change, percent
+                                "*{}*\n{}  {}\n{}  {}\n{}",
+                                quote.name,
+                                price,
+                                volume,
+                                change,
+                                percent,
+                                session
                             ),
                             true,
                         );
@@ -66,8 +438,8 @@ pub async fn send_quotes(
         msg.channel_id
             .send_message(&ctx.http, |m| {
                 m.embed(|e| {
-                    for error in errors {
-                        e.field(error, "Error fetching quote", true);
+                    for (symbol, reason) in errors {
+                        e.field(symbol, reason, true);
                     }
                     e
                 })
@@ -76,3 +448,129 @@ pub async fn send_quotes(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    /// Friday 2020-08-14 16:00 EDT, the close
+    const CLOSE: i64 = 1_597_435_200_000;
+
+    fn quote(extended_time: Option<i64>) -> Quote {
+        serde_json::from_value(serde_json::json!({
+            "companyName": "Apple Inc",
+            "symbol": "AAPL",
+            "calculationPrice": "close",
+            "latestPrice": 100.0,
+            "latestSource": "Close",
+            "latestUpdate": CLOSE,
+            "closeTime": CLOSE,
+            "change": 1.0,
+            "changePercent": 0.01,
+            "extendedPrice": 1101.5,
+            "extendedChange": 1.5,
+            "extendedChangePercent": 0.015,
+            "extendedPriceTime": extended_time,
+        }))
+        .unwrap()
+    }
+
+    #[rstest(
+        stock,
+        result,
+        case::symbol("aapl", Ok("AAPL")),
+        case::name("Apple", Ok("AAPL")),
+        case::unknown("XYZZY", Err("Unknown symbol: `XYZZY`")),
+        case::mention("@everyone", Err("Unknown symbol: `@everyone`")),
+        case::backtick("`@here`", Err("Unknown symbol: `'@here'`"))
+    )]
+    fn resolve_stock(stock: &str, result: std::result::Result<&str, &str>) {
+        let universe = Universe::new(vec![
+            Symbol::test("AAPL", "Apple Inc", "cs"),
+            Symbol::test("MSFT", "Microsoft Corp", "cs"),
+        ]);
+
+        assert_eq!(
+            super::resolve_stock(&universe, stock),
+            result.map(str::to_string).map_err(str::to_string)
+        );
+    }
+
+    #[rstest(
+        value,
+        expected,
+        case::trillions(2.13e12, "$2.13T"),
+        case::billions(45.678e9, "$45.68B"),
+        case::millions(1.5e6, "$1.50M"),
+        case::below_million(999_999.0, "$999,999.00"),
+        case::small(12.3, "$12.30"),
+        case::zero(0.0, "$0.00"),
+        case::negative(-3.2e9, "-$3.20B"),
+        case::negative_small(-1234.5, "-$1,234.50")
+    )]
+    fn format_compact(value: f64, expected: &str) {
+        assert_eq!(super::format_compact(value), expected);
+    }
+
+    #[rstest(
+        seconds,
+        expected,
+        case::now(0, "just now"),
+        case::seconds(59, "just now"),
+        case::future(-120, "just now"),
+        case::minute(60, "1m ago"),
+        case::minutes(59 * 60, "59m ago"),
+        case::hour(60 * 60, "1h ago"),
+        case::hours(23 * 60 * 60 + 59 * 60, "23h ago"),
+        case::day(24 * 60 * 60, "1d ago"),
+        case::days(10 * 24 * 60 * 60, "10d ago")
+    )]
+    fn format_ago(seconds: i64, expected: &str) {
+        let now = Utc.timestamp_millis(CLOSE);
+        let time = now - chrono::Duration::seconds(seconds);
+
+        assert_eq!(super::format_ago(time, now), expected);
+    }
+
+    #[rstest(
+        extended_time,
+        now,
+        expected,
+        case::after_hours(
+            Some(1_597_440_600_000),
+            1_597_440_600_000,
+            Some("`After hours` $1,101.50 +1.50 (+1.50%) as of 5:30 PM EDT")
+        ),
+        case::pre_market(
+            Some(1_597_665_600_000),
+            1_597_669_200_000,
+            Some("`Pre-market` $1,101.50 +1.50 (+1.50%) as of 8:00 AM EDT")
+        ),
+        case::weekend(
+            Some(1_597_500_000_000),
+            1_597_665_600_000,
+            Some(
+                "`Extended hours` $1,101.50 +1.50 (+1.50%) \
+                 as of Sat Aug 15 10:00 AM EDT"
+            )
+        ),
+        case::at_close(Some(CLOSE), CLOSE, None),
+        case::before_close(Some(1_597_431_600_000), CLOSE, None),
+        case::missing(None, CLOSE, None)
+    )]
+    fn extended_hours(
+        extended_time: Option<i64>,
+        now: i64,
+        expected: Option<&str>,
+    ) {
+        let result = super::extended_hours(
+            &quote(extended_time),
+            Utc.timestamp_millis(now),
+        );
+
+        assert_eq!(result.as_deref(), expected);
+    }
+}
@@ -0,0 +1,278 @@
+//
+
+use super::utils;
+
+use crate::iex;
+use crate::storage::{self, MAX_WATCHLIST};
+use crate::utils::inline_code;
+
+use serenity::{
+    client::Context,
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+};
+
+use itertools::Itertools;
+use log::error;
+
+async fn watchlist_symbols(
+    ctx: &Context,
+    msg: &Message,
+) -> Option<Vec<String>> {
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            return None;
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    match storage::blocking(db, move |db| db.watchlist(guild, user)).await {
+        Ok(symbols) => Some(symbols),
+        Err(why) => {
+            error!("Could not load watchlist: {:?}", why);
+            None
+        }
+    }
+}
+
+async fn show(ctx: &Context, msg: &Message) -> CommandResult {
+    let content = match watchlist_symbols(ctx, msg).await {
+        Some(symbols) if symbols.is_empty() => {
+            "Your watchlist is empty, add to it with `!watch add SYMBOL`"
+                .to_string()
+        }
+        Some(symbols) => format!("Watching {}", symbols.join(", ")),
+        None => "Command Error".to_string(),
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[sub_commands(add, remove, list)]
+#[usage("add|remove|list [SYMBOL ...]")]
+async fn watch(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    show(ctx, msg).await
+}
+
+#[command]
+#[min_args(1)]
+#[usage("SYMBOL [SYMBOL ...]")]
+async fn add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let stocks = utils::parse_symbols(&mut args);
+
+    let universe = match iex::cache::universe() {
+        Some(u) => u,
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (known, unknown) = utils::partition_listed(&universe, stocks);
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let symbols = known.clone();
+    let added =
+        storage::blocking(db, move |db| db.watch(guild, user, &symbols))
+            .await?;
+
+    let mut lines = Vec::new();
+
+    if !added.is_empty() {
+        lines.push(format!("Added {} to your watchlist", added.join(", ")));
+    }
+
+    let skipped: Vec<&String> =
+        known.iter().filter(|s| !added.contains(s)).collect();
+    if !skipped.is_empty() {
+        lines.push(format!(
+            "Not added {} (already watched, or the list is at its limit \
+             of {})",
+            skipped.iter().format(", "),
+            MAX_WATCHLIST
+        ));
+    }
+
+    if !unknown.is_empty() {
+        lines.push(format!(
+            "{}: {}",
+            utils::UNKNOWN,
+            unknown.iter().map(|s| inline_code(s)).format(", ")
+        ));
+    }
+
+    if lines.is_empty() {
+        lines.push("No symbols found".to_string());
+    }
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(lines.join("\n")))
+        .await?;
+    Ok(())
+}
+
+#[command]
+#[aliases(rm, del)]
+#[min_args(1)]
+#[usage("SYMBOL [SYMBOL ...]")]
+async fn remove(
+    ctx: &Context,
+    msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    // Spelled the way `add` stored them, anything no longer listed is
+    // removed as given
+    let stocks = match iex::cache::universe() {
+        Some(universe) => {
+            utils::listed_forms(&universe, utils::parse_symbols(&mut args))
+        }
+        None => utils::parse_symbols(&mut args),
+    };
+
+    let db = match utils::database(ctx).await {
+        Some(db) => db,
+        None => {
+            error!("Could not get database");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let (guild, user) = (msg.guild_id, msg.author.id);
+    let removed =
+        storage::blocking(db, move |db| db.unwatch(guild, user, &stocks))
+            .await?;
+
+    let content = if removed.is_empty() {
+        "None of those are on your watchlist".to_string()
+    } else {
+        format!("Removed {} from your watchlist", removed.join(", "))
+    };
+
+    msg.channel_id
+        .send_message(&ctx.http, |m| m.content(content))
+        .await?;
+    Ok(())
+}
+
+#[command]
+async fn list(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    show(ctx, msg).await
+}
+
+#[command]
+async fn watchlist(
+    ctx: &Context,
+    msg: &Message,
+    _args: Args,
+) -> CommandResult {
+    let stocks = match watchlist_symbols(ctx, msg).await {
+        Some(s) => s,
+        None => {
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if stocks.is_empty() {
+        return show(ctx, msg).await;
+    }
+
+    let filter = utils::listing_filter(ctx, msg).await;
+    let client = match utils::iex_client(ctx).await {
+        Some(c) => c,
+        None => {
+            error!("Could not get iex client");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match utils::fetch_quotes(&client, &filter, stocks).await {
+        Some((quotes, errors)) => {
+            utils::send_quotes(ctx, msg, quotes, errors).await?
+        }
+        None => {
+            error!("Could not get list of symbols");
+            msg.channel_id
+                .send_message(&ctx.http, |m| m.content("Command Error"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::iex::{Symbol, Universe};
+    use crate::storage::Storage;
+    use crate::utils::normalize_symbol;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serenity::model::id::UserId;
+
+    #[rstest(
+        added,
+        removed,
+        case::dot("BAC.L", "BAC.L"),
+        case::dash("BAC-L", "bac.l"),
+        case::slash("BAC/L", "BAC-L"),
+        case::delisted("OLD", "OLD")
+    )]
+    fn remove_round_trip(added: &str, removed: &str) {
+        let universe = Universe::new(vec![
+            Symbol::test("BAC-L", "Bank of America Corp", "ps"),
+            Symbol::test("OLD", "Old Co", "cs"),
+        ]);
+        let storage = Storage::in_memory().unwrap();
+
+        let (known, _) =
+            utils::partition_listed(&universe, vec![normalize_symbol(added)]);
+        storage.watch(None, UserId(1), &known).unwrap();
+
+        // Removing a symbol after it is delisted uses it as given
+        let universe = Universe::new(vec![Symbol::test(
+            "BAC-L",
+            "Bank of America Corp",
+            "ps",
+        )]);
+        let stocks =
+            utils::listed_forms(&universe, vec![normalize_symbol(removed)]);
+
+        assert_eq!(
+            storage.unwatch(None, UserId(1), &stocks).unwrap(),
+            vec![known[0].clone()]
+        );
+        assert!(storage.watchlist(None, UserId(1)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+//
+
+use crate::iex::{self, Symbol};
+
+use std::fmt;
+
+/// Issue types eligible unless a guild configures otherwise, common
+/// stock and ETFs
+pub const DEFAULT_ISSUE_TYPES: &[&str] = &["cs", "et"];
+
+/// Which listings a guild treats as stocks, for implicit detection and
+/// for quotes
+///
+/// Disabled listings are never eligible. An empty list of exchanges
+/// allows every exchange.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingFilter {
+    pub issue_types: Vec<String>,
+    pub exchanges: Vec<String>,
+}
+
+impl Default for ListingFilter {
+    fn default() -> Self {
+        ListingFilter {
+            issue_types: DEFAULT_ISSUE_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            exchanges: Vec::new(),
+        }
+    }
+}
+
+impl ListingFilter {
+    pub fn allows(&self, symbol: &Symbol) -> bool {
+        symbol.enabled
+            && self.issue_types.contains(&symbol.issue_type)
+            && (self.exchanges.is_empty()
+                || self.exchanges.contains(&symbol.exchange))
+    }
+
+    /// Why a listing is not eligible, for replies to commands
+    pub fn reason(&self, symbol: &Symbol) -> Option<String> {
+        if !symbol.enabled {
+            Some("Listing is disabled".to_string())
+        } else if !self.issue_types.contains(&symbol.issue_type) {
+            Some(format!("{} is not enabled here", symbol.issue_type_name()))
+        } else if !self.allows(symbol) {
+            Some(format!("{} is not enabled here", symbol.exchange))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ListingFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let types: Vec<&str> = self
+            .issue_types
+            .iter()
+            .map(|t| iex::issue_type_name(t))
+            .collect();
+
+        write!(f, "Issue types: {}\nExchanges: ", types.join(", "))?;
+        if self.exchanges.is_empty() {
+            write!(f, "any")
+        } else {
+            write!(f, "{}", self.exchanges.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ListingFilter;
+    use crate::iex::Symbol;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        issue_type, exchange, enabled, exchanges, result,
+        case::common("cs", "NAS", true, &[], true),
+        case::etf("et", "PSE", true, &[], true),
+        case::warrant("wt", "NAS", true, &[], false),
+        case::disabled("cs", "NAS", false, &[], false),
+        case::exchange("cs", "NYS", true, &["NYS"], true),
+        case::other_exchange("cs", "NAS", true, &["NYS"], false),
+    )]
+    fn allows(
+        issue_type: &str,
+        exchange: &str,
+        enabled: bool,
+        exchanges: &[&str],
+        result: bool,
+    ) {
+        let mut symbol = Symbol::test("TEST", "Test Inc", issue_type);
+        symbol.exchange = exchange.to_string();
+        symbol.enabled = enabled;
+
+        let filter = ListingFilter {
+            exchanges: exchanges.iter().map(|e| e.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(filter.allows(&symbol), result);
+    }
+
+    #[rstest(
+        issue_type, enabled, exchanges, result,
+        case::allowed("cs", true, &[], None),
+        case::disabled("cs", false, &[], Some("Listing is disabled")),
+        case::disabled_type("wt", false, &[], Some("Listing is disabled")),
+        case::issue_type("wt", true, &[], Some("Warrant is not enabled here")),
+        case::exchange("cs", true, &["NYS"], Some("NAS is not enabled here")),
+    )]
+    fn reason(
+        issue_type: &str,
+        enabled: bool,
+        exchanges: &[&str],
+        result: Option<&str>,
+    ) {
+        let mut symbol = Symbol::test("TEST", "Test Inc", issue_type);
+        symbol.exchange = "NAS".to_string();
+        symbol.enabled = enabled;
+
+        let filter = ListingFilter {
+            exchanges: exchanges.iter().map(|e| e.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(filter.reason(&symbol).as_deref(), result);
+    }
+}
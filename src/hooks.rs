@@ -1,7 +1,8 @@
 //
 
+use crate::storage;
 use crate::utils;
-use crate::ChannelLastStocks;
+use crate::Database;
 
 use itertools::Itertools;
 use serenity::{
@@ -69,13 +70,21 @@ pub(crate) async fn normal_message(ctx: &Context, msg: &Message) {
     match utils::extract_stocks(ctx, msg).await {
         Ok(stocks) => {
             debug!("Found stonks in message: {}", stocks.iter().format(", "));
-            {
-                let mut data = ctx.data.write().await;
-                if let Some(last) = data.get_mut::<ChannelLastStocks>() {
-                    last.insert(msg.channel_id, stocks);
-                } else {
-                    error!("Could not get last stock cache");
+            let db = match ctx.data.read().await.get::<Database>() {
+                Some(db) => db.clone(),
+                None => {
+                    error!("Could not get database");
+                    return;
                 }
+            };
+
+            let channel = msg.channel_id;
+            let result = storage::blocking(db, move |db| {
+                db.set_last_stocks(channel, &stocks)
+            })
+            .await;
+            if let Err(why) = result {
+                error!("Could not save last stocks: {:?}", why);
             }
         }
         Err(why) => error!("Could not extract symbols: {:?}", why),
@@ -1,13 +1,24 @@
 //
 
 use super::client::Client;
+use super::error::Result;
 use super::reference::Reference;
+use super::stock::{Quote, Stock};
+use super::universe::Universe;
+use crate::calendar;
 
-use cached::proc_macro::cached;
 use cached::Cached;
-
-use log::error;
-use std::{cmp::Eq, collections::HashMap, hash::Hash, time::Instant};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use std::{
+    cmp::Eq,
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex as AsyncMutex, time::delay_for};
 
 /// Enum used for defining the status of time-cached values
 enum Status {
@@ -18,8 +29,8 @@ enum Status {
 
 /// Cache store bound by time
 ///
-/// Values are timestamped when inserted and are
-/// evicted if expired at time of retrieval.
+/// Values are given an expiry when inserted, from the lifespan at the
+/// time, and are evicted if expired at time of retrieval.
 ///
 /// Note: This cache is in-memory only
 pub struct TimedCache<K, V> {
@@ -27,58 +38,31 @@ pub struct TimedCache<K, V> {
     seconds: u64,
     hits: u64,
     misses: u64,
-    initial_capacity: Option<usize>,
 }
 
 impl<K: Hash + Eq, V> TimedCache<K, V> {
-    #[allow(dead_code)]
     /// Creates a new `TimedCache` with a specified lifespan
     pub fn with_lifespan(seconds: u64) -> TimedCache<K, V> {
         TimedCache {
-            store: Self::new_store(None),
+            store: HashMap::new(),
             seconds,
             hits: 0,
             misses: 0,
-            initial_capacity: None,
         }
     }
 
-    #[allow(dead_code)]
-    /// Creates a new `TimedCache` with a specified lifespan and
-    /// cache-store with the specified pre-allocated capacity
-    pub fn with_lifespan_and_capacity(
-        seconds: u64,
-        size: usize,
-    ) -> TimedCache<K, V> {
-        TimedCache {
-            store: Self::new_store(Some(size)),
-            seconds,
-            hits: 0,
-            misses: 0,
-            initial_capacity: Some(size),
+    fn status(&self, key: &K) -> Status {
+        match self.store.get(key) {
+            Some(&(expires, _)) if Instant::now() < expires => Status::Found,
+            Some(_) => Status::Expired,
+            None => Status::NotFound,
         }
     }
-
-    fn new_store(capacity: Option<usize>) -> HashMap<K, (Instant, V)> {
-        capacity.map_or_else(HashMap::new, HashMap::with_capacity)
-    }
 }
 
 impl<K: Hash + Eq, V> Cached<K, V> for TimedCache<K, V> {
     fn cache_get(&mut self, key: &K) -> Option<&V> {
-        let status = {
-            let val = self.store.get(key);
-            if let Some(&(instant, _)) = val {
-                if instant.elapsed().as_secs() < self.seconds {
-                    Status::Found
-                } else {
-                    Status::Expired
-                }
-            } else {
-                Status::NotFound
-            }
-        };
-        match status {
+        match self.status(key) {
             Status::NotFound => {
                 self.misses += 1;
                 None
@@ -96,19 +80,7 @@ impl<K: Hash + Eq, V> Cached<K, V> for TimedCache<K, V> {
     }
 
     fn cache_get_mut(&mut self, key: &K) -> Option<&mut V> {
-        let status = {
-            let val = self.store.get(key);
-            if let Some(&(instant, _)) = val {
-                if instant.elapsed().as_secs() < self.seconds {
-                    Status::Found
-                } else {
-                    Status::Expired
-                }
-            } else {
-                Status::NotFound
-            }
-        };
-        match status {
+        match self.status(key) {
             Status::NotFound => {
                 self.misses += 1;
                 None
@@ -125,8 +97,8 @@ impl<K: Hash + Eq, V> Cached<K, V> for TimedCache<K, V> {
         }
     }
     fn cache_set(&mut self, key: K, val: V) {
-        let stamped = (Instant::now(), val);
-        self.store.insert(key, stamped);
+        let expires = Instant::now() + Duration::from_secs(self.seconds);
+        self.store.insert(key, (expires, val));
     }
     fn cache_remove(&mut self, k: &K) -> Option<V> {
         self.store.remove(k).map(|(_, v)| v)
@@ -135,7 +107,7 @@ impl<K: Hash + Eq, V> Cached<K, V> for TimedCache<K, V> {
         self.store.clear();
     }
     fn cache_reset(&mut self) {
-        self.store = Self::new_store(self.initial_capacity);
+        self.store = HashMap::new();
     }
     fn cache_size(&self) -> usize {
         self.store.len()
@@ -152,6 +124,8 @@ impl<K: Hash + Eq, V> Cached<K, V> for TimedCache<K, V> {
 }
 
 trait Lifespan {
+    /// Sets the lifespan of values inserted from now on, those already in
+    /// the cache keep their expiry
     fn cache_set_lifespan(&mut self, seconds: u64);
 }
 
@@ -161,23 +135,358 @@ impl<K: Hash + Eq, V> Lifespan for TimedCache<K, V> {
     }
 }
 
-#[cached(
-    type = "TimedCache<u8, Option<Vec<String>>>",
-    create = "{ TimedCache::with_lifespan(0) }",
-    option = true,
-    key = "u8",
-    convert = r#"{ 0 }"#
-)]
-pub async fn symbols(client: &Client) -> Option<Vec<String>> {
-    match client.symbols().await {
-        Ok(s) => Some(s.iter().map(|x| x.symbol.clone()).collect()),
+/// How soon a failed refresh of the symbols is retried
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Shortest wait between refreshes of the symbols, whatever is configured
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A list of symbols and when it was fetched
+struct Loaded {
+    universe: Arc<Universe>,
+    at: Instant,
+}
+
+/// Recently fetched quotes and how long they are reused
+struct Quotes {
+    cache: TimedCache<String, Quote>,
+    open: u64,
+    closed: u64,
+}
+
+impl Quotes {
+    /// Caches `quotes` for as long as the market hours `now` allow
+    fn insert(&mut self, quotes: &HashMap<String, Quote>, now: DateTime<Utc>) {
+        let lifespan = if calendar::is_regular_hours(now) {
+            self.open
+        } else {
+            self.closed
+        };
+        self.cache.cache_set_lifespan(lifespan);
+
+        for (symbol, quote) in quotes {
+            self.cache.cache_set(symbol.clone(), quote.clone());
+        }
+    }
+}
+
+lazy_static! {
+    static ref SYMBOLS: RwLock<Option<Loaded>> = RwLock::new(None);
+    static ref QUOTES: Mutex<Quotes> = Mutex::new(Quotes {
+        cache: TimedCache::with_lifespan(0),
+        open: 0,
+        closed: 0,
+    });
+    static ref IN_FLIGHT: Mutex<HashMap<String, Arc<AsyncMutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The most recently fetched list of symbols
+///
+/// The list is only replaced by a successful refresh, so it may be
+/// older than the refresh interval while IEX is failing. `None` until
+/// the first refresh succeeds.
+pub fn universe() -> Option<Arc<Universe>> {
+    let symbols = SYMBOLS.read().ok()?;
+    symbols.as_ref().map(|l| Arc::clone(&l.universe))
+}
+
+/// How long ago the current list of symbols was fetched
+pub fn symbols_age() -> Option<Duration> {
+    let symbols = SYMBOLS.read().ok()?;
+    symbols.as_ref().map(|l| l.at.elapsed())
+}
+
+/// Fetches the symbols, replacing the current list if that succeeds
+async fn refresh_symbols(client: &Client) -> bool {
+    let universe = match client.symbols().await {
+        Ok(s) => Universe::new(s),
         Err(why) => {
             error!("Could not fetch symbols: {:?}", why);
-            None
+            return false;
         }
+    };
+
+    if universe.is_empty() {
+        error!("IEX returned an empty symbol list");
+        return false;
+    }
+
+    info!("Loaded {} symbols", universe.len());
+    match SYMBOLS.write() {
+        Ok(mut symbols) => {
+            *symbols = Some(Loaded {
+                universe: Arc::new(universe),
+                at: Instant::now(),
+            });
+            true
+        }
+        Err(_) => {
+            error!("Symbol cache poisoned");
+            false
+        }
+    }
+}
+
+/// Keeps the symbols fresh, refetching them every `interval`
+///
+/// Messages never wait on IEX for the list, a failed refresh keeps the
+/// previous list and is retried sooner than `interval`. An `interval`
+/// shorter than a minute is raised to one.
+pub async fn refresh(client: Client, interval: Duration) {
+    let interval = interval.max(MIN_INTERVAL);
+
+    loop {
+        let wait = if refresh_symbols(&client).await {
+            interval
+        } else {
+            match symbols_age() {
+                Some(age) => warn!(
+                    "Serving symbols fetched {} minutes ago",
+                    age.as_secs() / 60
+                ),
+                None => warn!("No symbols loaded, detection is disabled"),
+            }
+            RETRY_INTERVAL.min(interval)
+        };
+
+        delay_for(wait).await;
+    }
+}
+
+/// Sets how many seconds quotes are reused during regular trading hours
+/// and outside of them, when prices rarely move
+pub fn set_quote_lifetimes(open: u64, closed: u64) {
+    if let Ok(mut quotes) = QUOTES.lock() {
+        quotes.open = open;
+        quotes.closed = closed;
     }
 }
 
-pub async fn set_symbols_lifetime(seconds: u64) {
-    SYMBOLS.lock().await.cache_set_lifespan(seconds);
+/// The quotes for `symbols` that are still fresh
+fn cached_quotes(symbols: &[&str]) -> HashMap<String, Quote> {
+    let mut quotes = match QUOTES.lock() {
+        Ok(q) => q,
+        Err(_) => return HashMap::new(),
+    };
+
+    symbols
+        .iter()
+        .filter_map(|s| {
+            let symbol = s.to_string();
+            let quote = quotes.cache.cache_get(&symbol)?.clone();
+            Some((symbol, quote))
+        })
+        .collect()
+}
+
+/// Quotes for `symbols`, keyed by symbol, reusing recently fetched ones
+///
+/// Only one request to IEX is made for a symbol at a time, concurrent
+/// requests for it wait for that one and use its quote.
+pub async fn quotes(
+    client: &Client,
+    symbols: &[&str],
+) -> Result<HashMap<String, Quote>> {
+    let mut found = cached_quotes(symbols);
+
+    let mut missing: Vec<&str> = symbols
+        .iter()
+        .copied()
+        .filter(|s| !found.contains_key(*s))
+        .collect();
+    if missing.is_empty() {
+        return Ok(found);
+    }
+
+    // Sorted so overlapping requests take the locks in the same order
+    missing.sort_unstable();
+    missing.dedup();
+
+    let locks: Vec<Arc<AsyncMutex<()>>> = match IN_FLIGHT.lock() {
+        Ok(mut in_flight) => missing
+            .iter()
+            .map(|s| {
+                Arc::clone(
+                    in_flight
+                        .entry(s.to_string())
+                        .or_insert_with(|| Arc::new(AsyncMutex::new(()))),
+                )
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let mut guards = Vec::with_capacity(locks.len());
+    for lock in &locks {
+        guards.push(lock.lock().await);
+    }
+
+    // Fetched by whoever held the locks while we waited
+    let fetched = cached_quotes(&missing);
+    missing.retain(|s| !fetched.contains_key(*s));
+    found.extend(fetched);
+
+    let result = if missing.is_empty() {
+        Ok(())
+    } else {
+        client.quotes(&missing).await.map(|quotes| {
+            if let Ok(mut cached) = QUOTES.lock() {
+                cached.insert(&quotes, Utc::now());
+            }
+            found.extend(quotes);
+        })
+    };
+
+    drop(guards);
+    drop(locks);
+
+    // Locks are only cloned under IN_FLIGHT, so one held by the map alone
+    // has nobody waiting on it
+    if let Ok(mut in_flight) = IN_FLIGHT.lock() {
+        in_flight.retain(|_, lock| Arc::strong_count(lock) > 1);
+    }
+
+    result.map(|_| found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::iex::mock::{self, Response, Server};
+
+    use lazy_static::lazy_static;
+    use pretty_assertions::assert_eq;
+    use tokio::time::timeout;
+
+    lazy_static! {
+        /// Held by tests that change the quote lifetimes
+        static ref LIFETIMES: AsyncMutex<()> = AsyncMutex::new(());
+    }
+
+    fn symbols(symbols: &[&str]) -> String {
+        let symbols: Vec<String> = symbols
+            .iter()
+            .map(|s| {
+                format!(
+                    r#"{{"symbol":"{}","exchange":"NAS","name":"{} Inc",
+                    "date":"2020-08-14","isEnabled":true,"type":"cs",
+                    "region":"US","currency":"USD","iexId":"IEX_{}",
+                    "figi":null,"cik":null}}"#,
+                    s, s, s
+                )
+            })
+            .collect();
+        format!("[{}]", symbols.join(","))
+    }
+
+    fn in_flight(symbol: &str) -> bool {
+        IN_FLIGHT.lock().unwrap().contains_key(symbol)
+    }
+
+    #[test]
+    fn lifespan_per_entry() {
+        let mut cache = TimedCache::with_lifespan(60);
+        cache.cache_set("long", 1);
+        cache.cache_set_lifespan(0);
+        cache.cache_set("short", 2);
+
+        // Changing the lifespan leaves earlier values alone
+        assert_eq!(cache.cache_get(&"long"), Some(&1));
+        assert_eq!(cache.cache_get(&"short"), None);
+        assert_eq!(cache.cache_size(), 1);
+        assert_eq!(cache.cache_hits(), Some(1));
+        assert_eq!(cache.cache_misses(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn single_flight() {
+        let _lifetimes = LIFETIMES.lock().await;
+        set_quote_lifetimes(60, 60);
+
+        let server = Server::start(vec![Response::ok(&mock::batch(&["FLY"]))
+            .delay(Duration::from_millis(200))]);
+        let client = mock::client(server.url(), 1);
+
+        let (first, second) =
+            tokio::join!(quotes(&client, &["FLY"]), quotes(&client, &["FLY"]));
+
+        assert_eq!(first.unwrap()["FLY"].price, 100.0);
+        assert_eq!(second.unwrap()["FLY"].price, 100.0);
+        assert_eq!(server.requests().len(), 1);
+        assert!(!in_flight("FLY"));
+    }
+
+    #[tokio::test]
+    async fn lifetime_switch() {
+        let _lifetimes = LIFETIMES.lock().await;
+        let server = Server::start(vec![Response::ok(&mock::batch(&["TTL"]))]);
+        let client = mock::client(server.url(), 1);
+
+        // Nothing is reused while quotes have no lifetime
+        set_quote_lifetimes(0, 0);
+        quotes(&client, &["TTL"]).await.unwrap();
+        quotes(&client, &["TTL"]).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+
+        set_quote_lifetimes(60, 60);
+        quotes(&client, &["TTL"]).await.unwrap();
+        quotes(&client, &["TTL"]).await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+
+        // Shortening the lifetime keeps the quote already cached
+        set_quote_lifetimes(0, 0);
+        quotes(&client, &["TTL"]).await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+        assert!(!in_flight("TTL"));
+    }
+
+    #[tokio::test]
+    async fn failed_fetch() {
+        let server = Server::start(vec![Response::new(500, "down")]);
+
+        assert!(quotes(&mock::client(server.url(), 1), &["FAIL"])
+            .await
+            .is_err());
+        assert!(!in_flight("FAIL"));
+    }
+
+    // The list is global, so every step runs in order in one test
+    #[tokio::test]
+    async fn symbols_refresh() {
+        let server = Server::start(vec![
+            Response::new(500, "down"),
+            Response::ok(&symbols(&["AAPL", "MSFT"])),
+            Response::ok("[]"),
+            Response::new(500, "down"),
+            Response::ok(&symbols(&["AAPL", "MSFT", "TSLA"])),
+        ]);
+        let client = mock::client(server.url(), 1);
+
+        // Nothing is served until a refresh succeeds
+        assert!(!refresh_symbols(&client).await);
+        assert!(universe().is_none());
+        assert!(symbols_age().is_none());
+
+        assert!(refresh_symbols(&client).await);
+        assert_eq!(universe().unwrap().len(), 2);
+        assert!(symbols_age().unwrap() < Duration::from_secs(5));
+
+        // An empty list or an error keeps the previous one
+        assert!(!refresh_symbols(&client).await);
+        assert_eq!(universe().unwrap().len(), 2);
+        assert!(!refresh_symbols(&client).await);
+        assert_eq!(universe().unwrap().len(), 2);
+
+        // A zero interval is raised to the minimum rather than spinning
+        let result = timeout(
+            Duration::from_millis(500),
+            refresh(client, Duration::from_secs(0)),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(universe().unwrap().len(), 3);
+        assert_eq!(server.requests().len(), 5);
+    }
 }
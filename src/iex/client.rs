@@ -1,27 +1,107 @@
 //
 
-// TODO(jkoelker) switch to thiserror
-use anyhow::{anyhow, Result};
-use reqwest::{Method, StatusCode};
+use super::error::{Error, Result};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, Method, StatusCode};
 use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+use tokio::time::delay_for;
 
 use log::{debug, warn};
 
+/// How failed idempotent requests are retried
+///
+/// Delays grow exponentially from `base_delay`, capped at `max_delay`,
+/// with jitter applied to each. A `Retry-After` from the server takes
+/// precedence over the computed delay.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Give up rather than wait past this much time since the first attempt
+    pub max_elapsed: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(8),
+            max_elapsed: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Jittered delay before the attempt following `attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .checked_mul(1 << attempt.saturating_sub(1).min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        exp.mul_f64(rand::thread_rng().gen_range(0.5, 1.0))
+    }
+}
+
 #[derive(Clone)]
 pub struct Client {
     token: String,
     url: reqwest::Url,
     client: reqwest::Client,
+    retry: RetryPolicy,
 }
 
-fn handle_errors(body: &str, s: StatusCode, u: reqwest::Url) -> Result<()> {
+fn handle_errors(
+    body: &str,
+    s: StatusCode,
+    u: &str,
+    retry_after: Option<Duration>,
+) -> Result<()> {
     if s.is_client_error() || s.is_server_error() {
-        Err(anyhow!("({:?}) {}: {}", s, body, u))
+        Err(Error::from_status(
+            s,
+            body.to_string(),
+            u.to_string(),
+            retry_after,
+        ))
     } else {
         Ok(())
     }
 }
 
+/// Parses a `Retry-After` header, given either as seconds or as an HTTP
+/// date relative to `now`
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let when = DateTime::parse_from_rfc2822(value).ok()?;
+
+    // A date already past means the request may be retried right away
+    Some(
+        (when.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_else(|_| Duration::from_secs(0)),
+    )
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT
+    )
+}
+
 impl Client {
     pub fn new(token: String) -> Self {
         let host = if token.starts_with('T') {
@@ -39,36 +119,326 @@ impl Client {
             client: reqwest::Client::builder()
                 .build()
                 .expect("Unable to build client"),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Points the client at another IEX compatible host, such as a local
+    /// stand-in server
+    pub fn with_base_url(mut self, base_url: &str) -> Result<Self> {
+        let mut url = reqwest::Url::parse(base_url)
+            .map_err(|e| Error::Url(e.to_string()))?;
+
+        // Paths are joined relative to the base, which drops its last
+        // segment unless it ends in a slash
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
         }
+
+        self.url = url;
+        Ok(self)
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
-        Ok(self.request::<T>(Method::GET, path).await?)
+        Ok(self.request::<T>(Method::GET, path, &[]).await?)
+    }
+
+    pub async fn get_with_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        Ok(self.request::<T>(Method::GET, path, query).await?)
     }
 
     async fn request<T: DeserializeOwned>(
         &self,
         method: Method,
         path: &str,
+        query: &[(&str, &str)],
     ) -> Result<T> {
-        let url = self.url.join(path)?;
+        let mut url =
+            self.url.join(path).map_err(|e| Error::Url(e.to_string()))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        // Only the token-free form of the url is logged or put in errors
+        let display = url.to_string();
+        url.query_pairs_mut().append_pair("token", &self.token);
+
+        let start = Instant::now();
+        let mut attempt = 1;
+
+        let text = loop {
+            match self.send(method.clone(), url.clone(), &display).await {
+                Ok(text) => break text,
+                Err(why)
+                    if why.is_retryable()
+                        && is_idempotent(&method)
+                        && attempt < self.retry.max_attempts =>
+                {
+                    let delay = why
+                        .retry_after()
+                        .unwrap_or_else(|| self.retry.backoff(attempt));
+
+                    if start.elapsed() + delay > self.retry.max_elapsed {
+                        return Err(why);
+                    }
+
+                    debug!(
+                        "Retry {}/{} for {} in {:?}: {}",
+                        attempt,
+                        self.retry.max_attempts - 1,
+                        display,
+                        delay,
+                        why
+                    );
+
+                    delay_for(delay).await;
+                    attempt += 1;
+                }
+                Err(why) => return Err(why),
+            }
+        };
+
+        if attempt > 1 {
+            debug!("{} succeeded after {} attempts", display, attempt);
+        }
+
+        serde_json::from_str(&text).map_err(|e| {
+            warn!("{}, {:?}", text, e);
+            Error::Decode {
+                source: e,
+                url: display,
+            }
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        url: reqwest::Url,
+        display: &str,
+    ) -> Result<String> {
         let res = self
             .client
-            .request(method, url.clone())
-            .query(&[("token", self.token.clone())])
+            .request(method, url)
             .send()
-            .await?;
+            .await
+            .map_err(|e| Error::transport(e, display.to_string()))?;
 
         let status = res.status();
-        let text = res.text().await?;
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, Utc::now()));
+        let text = res
+            .text()
+            .await
+            .map_err(|e| Error::transport(e, display.to_string()))?;
 
-        debug!("Status = {:?} for {}", status, url);
+        debug!("Status = {:?} for {}", status, display);
 
-        handle_errors(&text, status, url)?;
+        handle_errors(&text, status, display, retry_after)?;
 
-        serde_json::from_str(&text).map_err(|e| {
-            warn!("{}, {:?}", text, e);
-            anyhow!(e)
-        })
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_idempotent, parse_retry_after, Client, RetryPolicy};
+    use crate::iex::mock::{self, Response, Server};
+    use crate::iex::{Error, Result};
+
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use reqwest::Method;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    #[rstest(
+        base,
+        result,
+        case::host("http://localhost:8080", "http://localhost:8080/stock"),
+        case::host_slash(
+            "http://localhost:8080/",
+            "http://localhost:8080/stock"
+        ),
+        case::path(
+            "http://localhost:8080/stable",
+            "http://localhost:8080/stable/stock"
+        ),
+        case::path_slash(
+            "http://localhost:8080/stable/",
+            "http://localhost:8080/stable/stock"
+        )
+    )]
+    fn base_url(base: &str, result: &str) -> Result<()> {
+        let client =
+            Client::new("Tsk_test".to_string()).with_base_url(base)?;
+
+        assert_eq!(client.url.join("stock").unwrap().as_str(), result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn base_url_invalid() {
+        assert!(Client::new("Tsk_test".to_string())
+            .with_base_url("not a url")
+            .is_err());
+    }
+
+    #[rstest(
+        value,
+        result,
+        case::seconds("120", Some(120)),
+        case::padded(" 5 ", Some(5)),
+        case::date("Fri, 14 Aug 2020 14:31:00 GMT", Some(60)),
+        case::past("Fri, 14 Aug 2020 14:29:00 GMT", Some(0)),
+        case::negative("-5", None),
+        case::garbage("soon", None)
+    )]
+    fn retry_after(value: &str, result: Option<u64>) {
+        let now = Utc.ymd(2020, 8, 14).and_hms(14, 30, 0);
+
+        assert_eq!(
+            parse_retry_after(value, now),
+            result.map(Duration::from_secs)
+        );
+    }
+
+    #[rstest(
+        attempt,
+        max_ms,
+        case::first(1, 250),
+        case::second(2, 500),
+        case::third(3, 1000),
+        case::capped(10, 8000),
+        case::huge(u32::MAX, 8000)
+    )]
+    fn backoff(attempt: u32, max_ms: u64) {
+        let max = Duration::from_millis(max_ms);
+
+        for _ in 0..20 {
+            let delay = RetryPolicy::default().backoff(attempt);
+            assert!(delay >= max / 2 && delay <= max, "{:?}", delay);
+        }
+    }
+
+    #[rstest(
+        method,
+        result,
+        case::get(Method::GET, true),
+        case::head(Method::HEAD, true),
+        case::options(Method::OPTIONS, true),
+        case::put(Method::PUT, true),
+        case::post(Method::POST, false),
+        case::patch(Method::PATCH, false),
+        case::delete(Method::DELETE, false)
+    )]
+    fn idempotent(method: Method, result: bool) {
+        assert_eq!(is_idempotent(&method), result);
+    }
+
+    #[rstest(
+        status,
+        case::rate_limited(429),
+        case::unavailable(503),
+        case::internal(500)
+    )]
+    #[tokio::test]
+    async fn retries(status: u16) {
+        let server = Server::start(vec![
+            Response::new(status, "busy"),
+            Response::new(status, "busy"),
+            Response::ok("1"),
+        ]);
+
+        let value: u32 =
+            mock::client(server.url(), 3).get("ping").await.unwrap();
+
+        assert_eq!(value, 1);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_exhausted() {
+        let server = Server::start(vec![Response::new(503, "busy")]);
+
+        let err = mock::client(server.url(), 3)
+            .get::<u32>("ping")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Upstream { .. }), "{:?}", err);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[rstest(status, case::not_found(404), case::rejected(400))]
+    #[tokio::test]
+    async fn not_retried(status: u16) {
+        let server = Server::start(vec![Response::new(status, "no")]);
+
+        assert!(mock::client(server.url(), 3)
+            .get::<u32>("ping")
+            .await
+            .is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn not_idempotent() {
+        let server = Server::start(vec![Response::new(503, "busy")]);
+
+        let result = mock::client(server.url(), 3)
+            .request::<u32>(Method::POST, "ping", &[])
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[rstest(status, case::rate_limited(429), case::unavailable(503))]
+    #[tokio::test]
+    async fn retry_after_too_long(status: u16) {
+        let server = Server::start(vec![
+            Response::new(status, "busy").header("Retry-After", "60"),
+            Response::ok("1"),
+        ]);
+
+        let err = mock::client(server.url(), 3)
+            .get::<u32>("ping")
+            .await
+            .unwrap_err();
+
+        // Waiting would pass max_elapsed, so the error is returned
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(60)));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_date() {
+        let server = Server::start(vec![
+            Response::new(503, "busy")
+                .header("Retry-After", "Fri, 14 Aug 2020 14:30:00 GMT"),
+            Response::ok("1"),
+        ]);
+
+        // A date in the past is retried without waiting
+        let value: u32 =
+            mock::client(server.url(), 2).get("ping").await.unwrap();
+
+        assert_eq!(value, 1);
+        assert_eq!(server.requests().len(), 2);
     }
 }
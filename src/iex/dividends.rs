@@ -0,0 +1,53 @@
+//
+
+use super::client::Client;
+use super::error::Result;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// A dividend declared by a company, dates are `YYYY-MM-DD`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dividend {
+    pub ex_date: String,
+    pub payment_date: Option<String>,
+    pub amount: f64,
+    pub frequency: Option<String>,
+}
+
+/// A stock split of `from_factor` shares into `to_factor`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Split {
+    pub ex_date: String,
+    pub from_factor: f64,
+    pub to_factor: f64,
+}
+
+#[async_trait]
+pub trait Dividends {
+    async fn dividends(
+        &self,
+        symbol: &str,
+        range: &str,
+    ) -> Result<Vec<Dividend>>;
+    async fn splits(&self, symbol: &str, range: &str) -> Result<Vec<Split>>;
+}
+
+#[async_trait]
+impl Dividends for Client {
+    async fn dividends(
+        &self,
+        symbol: &str,
+        range: &str,
+    ) -> Result<Vec<Dividend>> {
+        let path = format!("stock/{}/dividends/{}", symbol, range);
+        Ok(self.get::<Vec<Dividend>>(&path).await?)
+    }
+
+    async fn splits(&self, symbol: &str, range: &str) -> Result<Vec<Split>> {
+        let path = format!("stock/{}/splits/{}", symbol, range);
+        Ok(self.get::<Vec<Split>>(&path).await?)
+    }
+}
@@ -0,0 +1,115 @@
+//
+
+use super::client::Client;
+use super::error::Result;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Reported earnings per share for a fiscal quarter
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EarningsReport {
+    #[serde(rename = "actualEPS")]
+    pub actual_eps: Option<f64>,
+    #[serde(rename = "consensusEPS")]
+    pub consensus_eps: Option<f64>,
+    #[serde(rename = "EPSReportDate")]
+    pub report_date: Option<String>,
+    pub fiscal_period: Option<String>,
+}
+
+impl EarningsReport {
+    /// How far the actual EPS beat the consensus, as a percentage of it
+    pub fn surprise_percent(&self) -> Option<f64> {
+        match (self.actual_eps, self.consensus_eps) {
+            (Some(actual), Some(consensus)) if consensus != 0.0 => {
+                Some((actual - consensus) / consensus.abs() * 100.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A company expected to report earnings soon
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingEarnings {
+    pub symbol: String,
+    /// `YYYY-MM-DD`
+    pub report_date: String,
+}
+
+/// IEX replies with an empty object for symbols without earnings
+#[derive(Debug, Deserialize)]
+struct History {
+    #[serde(default)]
+    earnings: Vec<EarningsReport>,
+}
+
+#[async_trait]
+pub trait Earnings {
+    async fn earnings(
+        &self,
+        symbol: &str,
+        last: usize,
+    ) -> Result<Vec<EarningsReport>>;
+    async fn upcoming_earnings(&self) -> Result<Vec<UpcomingEarnings>>;
+}
+
+#[async_trait]
+impl Earnings for Client {
+    async fn earnings(
+        &self,
+        symbol: &str,
+        last: usize,
+    ) -> Result<Vec<EarningsReport>> {
+        let path = format!("stock/{}/earnings/{}", symbol, last);
+        Ok(self.get::<History>(&path).await?.earnings)
+    }
+
+    async fn upcoming_earnings(&self) -> Result<Vec<UpcomingEarnings>> {
+        Ok(self
+            .get::<Vec<UpcomingEarnings>>("stock/market/upcoming-earnings")
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EarningsReport;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        actual,
+        consensus,
+        expected,
+        case::beat(Some(1.1), Some(1.0), Some(10.0)),
+        case::miss(Some(0.9), Some(1.0), Some(-10.0)),
+        case::smaller_loss(Some(-0.5), Some(-1.0), Some(50.0)),
+        case::larger_loss(Some(-1.5), Some(-1.0), Some(-50.0)),
+        case::zero_consensus(Some(0.1), Some(0.0), None),
+        case::no_consensus(Some(1.0), None, None),
+        case::not_reported(None, Some(1.0), None)
+    )]
+    fn surprise_percent(
+        actual: Option<f64>,
+        consensus: Option<f64>,
+        expected: Option<f64>,
+    ) {
+        let report = EarningsReport {
+            actual_eps: actual,
+            consensus_eps: consensus,
+            report_date: None,
+            fiscal_period: None,
+        };
+
+        let result = report.surprise_percent();
+        match (result, expected) {
+            (Some(r), Some(e)) => assert!((r - e).abs() < 1e-9, "{}", r),
+            (r, e) => assert_eq!(r, e),
+        }
+    }
+}
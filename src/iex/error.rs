@@ -0,0 +1,253 @@
+//
+
+use reqwest::StatusCode;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors returned by the IEX client
+///
+/// URLs carried by the variants never include the API token.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unknown symbol: {url}")]
+    UnknownSymbol { url: String },
+    #[error("authentication failed ({status}): {url}")]
+    Unauthorized { status: StatusCode, url: String },
+    #[error("message quota exhausted: {url}")]
+    QuotaExhausted { url: String },
+    #[error("rate limited: {url}")]
+    RateLimited {
+        url: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("upstream error ({status}) {body}: {url}")]
+    Upstream {
+        status: StatusCode,
+        body: String,
+        url: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("request rejected ({status}) {body}: {url}")]
+    Rejected {
+        status: StatusCode,
+        body: String,
+        url: String,
+    },
+    #[error("transport error ({reason}): {url}")]
+    Transport { reason: String, url: String },
+    #[error("could not decode response from {url}: {source}")]
+    Decode {
+        source: serde_json::Error,
+        url: String,
+    },
+    #[error("invalid url: {0}")]
+    Url(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Classifies an unsuccessful response by its status code
+    pub(crate) fn from_status(
+        status: StatusCode,
+        body: String,
+        url: String,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => Error::UnknownSymbol { url },
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Error::Unauthorized { status, url }
+            }
+            StatusCode::PAYMENT_REQUIRED => Error::QuotaExhausted { url },
+            StatusCode::TOO_MANY_REQUESTS => {
+                Error::RateLimited { url, retry_after }
+            }
+            s if s.is_server_error() => Error::Upstream {
+                status,
+                body,
+                url,
+                retry_after,
+            },
+            _ => Error::Rejected { status, body, url },
+        }
+    }
+
+    /// Wraps a reqwest error without keeping its url, which would
+    /// include the token in the query string
+    pub(crate) fn transport(err: reqwest::Error, url: String) -> Self {
+        let reason = if err.is_timeout() {
+            "operation timed out".to_string()
+        } else {
+            match std::error::Error::source(&err) {
+                Some(source) => source.to_string(),
+                None => "request failed".to_string(),
+            }
+        };
+
+        Error::Transport { reason, url }
+    }
+
+    /// Whether the request may succeed if tried again
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::RateLimited { .. }
+                | Error::Upstream { .. }
+                | Error::Transport { .. }
+        )
+    }
+
+    /// Whether the IEX plan does not include the data asked for
+    pub fn is_entitlement(&self) -> bool {
+        match self {
+            Error::QuotaExhausted { .. } => true,
+            Error::Unauthorized { status, .. } => {
+                *status == StatusCode::FORBIDDEN
+            }
+            _ => false,
+        }
+    }
+
+    /// How long the server asked us to wait before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimited { retry_after, .. }
+            | Error::Upstream { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// A short explanation suitable for showing to users
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Error::UnknownSymbol { .. } => "Unknown symbol",
+            Error::Unauthorized { .. } => "IEX rejected our credentials",
+            Error::QuotaExhausted { .. } => "IEX message quota exhausted",
+            Error::RateLimited { .. } => "Rate limited by IEX, try again soon",
+            Error::Upstream { .. } | Error::Transport { .. } => {
+                "IEX is unavailable"
+            }
+            Error::Rejected { .. } | Error::Decode { .. } | Error::Url(_) => {
+                "Error fetching data from IEX"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Error;
+    use crate::iex::mock::{self, Response, Server, TOKEN};
+
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    #[rstest(
+        status,
+        kind,
+        retryable,
+        entitlement,
+        case::not_found(404, "UnknownSymbol", false, false),
+        case::unauthorized(401, "Unauthorized", false, false),
+        case::forbidden(403, "Unauthorized", false, true),
+        case::payment(402, "QuotaExhausted", false, true),
+        case::too_many(429, "RateLimited", true, false),
+        case::internal(500, "Upstream", true, false),
+        case::unavailable(503, "Upstream", true, false),
+        case::bad_request(400, "Rejected", false, false),
+        case::teapot(418, "Rejected", false, false)
+    )]
+    fn from_status(
+        status: u16,
+        kind: &str,
+        retryable: bool,
+        entitlement: bool,
+    ) {
+        let err = Error::from_status(
+            StatusCode::from_u16(status).unwrap(),
+            "body".to_string(),
+            "url".to_string(),
+            Some(Duration::from_secs(1)),
+        );
+
+        assert!(format!("{:?}", err).starts_with(kind), "{:?}", err);
+        assert_eq!(err.is_retryable(), retryable);
+        assert_eq!(err.is_entitlement(), entitlement);
+    }
+
+    #[rstest(
+        status,
+        retry_after,
+        case::rate_limited(429, Some(Duration::from_secs(1))),
+        case::unavailable(503, Some(Duration::from_secs(1))),
+        case::rejected(400, None)
+    )]
+    fn from_status_retry_after(status: u16, retry_after: Option<Duration>) {
+        let err = Error::from_status(
+            StatusCode::from_u16(status).unwrap(),
+            String::new(),
+            String::new(),
+            Some(Duration::from_secs(1)),
+        );
+
+        assert_eq!(err.retry_after(), retry_after);
+    }
+
+    #[rstest(
+        status,
+        case::not_found(404),
+        case::forbidden(403),
+        case::upstream(500),
+        case::rejected(400)
+    )]
+    #[tokio::test]
+    async fn status_redacts_token(status: u16) {
+        let server = Server::start(vec![Response::new(status, "nope")]);
+
+        let err = mock::client(server.url(), 1)
+            .get::<serde_json::Value>("stock/aapl/quote")
+            .await
+            .unwrap_err();
+
+        // The token is sent, but never shown
+        assert!(server.requests()[0].contains(TOKEN));
+        assert!(!err.to_string().contains(TOKEN), "{}", err);
+        assert!(!format!("{:?}", err).contains(TOKEN), "{:?}", err);
+        assert!(err.to_string().contains("/stock/aapl/quote"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn decode_redacts_token() {
+        let server = Server::start(vec![Response::ok("not json")]);
+
+        let err = mock::client(server.url(), 1)
+            .get::<serde_json::Value>("stock/aapl/quote")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Decode { .. }), "{:?}", err);
+        assert!(!err.to_string().contains(TOKEN), "{}", err);
+        assert!(!format!("{:?}", err).contains(TOKEN), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn transport_redacts_token() {
+        // Bind then drop a listener to find a port nothing answers on
+        let url = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/", listener.local_addr().unwrap())
+        };
+
+        let err = mock::client(&url, 1)
+            .get::<serde_json::Value>("stock/aapl/quote")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Transport { .. }), "{:?}", err);
+        assert!(!err.to_string().contains(TOKEN), "{}", err);
+        assert!(!format!("{:?}", err).contains(TOKEN), "{:?}", err);
+    }
+}
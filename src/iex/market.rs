@@ -1,9 +1,9 @@
 //
 
 use super::client::Client;
+use super::error::Result;
 use super::stock::Quote;
 
-use anyhow::Result;
 use async_trait::async_trait;
 
 #[async_trait]
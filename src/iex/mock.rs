@@ -0,0 +1,175 @@
+//
+
+use super::client::{Client, RetryPolicy};
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Token sent by clients from `client`
+pub const TOKEN: &str = "Tsk_test";
+
+/// A client for `url` making at most `max_attempts` tries per request,
+/// with delays between them short enough for tests
+pub fn client(url: &str, max_attempts: u32) -> Client {
+    Client::new(TOKEN.to_string())
+        .with_base_url(url)
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            max_elapsed: Duration::from_secs(5),
+        })
+}
+
+/// A `stock/market/batch` reply with a quote for each of `symbols`
+pub fn batch(symbols: &[&str]) -> String {
+    let quotes: Vec<String> = symbols
+        .iter()
+        .map(|s| {
+            format!(
+                r#""{}":{{"quote":{{"companyName":"{} Inc","symbol":"{}",
+                "calculationPrice":"tops","latestPrice":100.0,
+                "latestSource":"IEX real time price","latestUpdate":0,
+                "change":1.0,"changePercent":0.01}}}}"#,
+                s, s, s
+            )
+        })
+        .collect();
+    format!("{{{}}}", quotes.join(","))
+}
+
+/// A canned response, served once per request in the order given
+#[derive(Clone, Debug)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    delay: Duration,
+}
+
+impl Response {
+    pub fn new(status: u16, body: &str) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+            delay: Duration::from_secs(0),
+        }
+    }
+
+    pub fn ok(body: &str) -> Self {
+        Response::new(200, body)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Waits `delay` before replying, as a slow upstream would
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+struct State {
+    responses: Vec<Response>,
+    requests: Vec<String>,
+}
+
+/// A local HTTP server standing in for IEX, serving the responses given
+/// to `start`
+///
+/// Once only one response is left it is repeated for every further
+/// request.
+pub struct Server {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl Server {
+    pub fn start(responses: Vec<Response>) -> Self {
+        assert!(!responses.is_empty(), "mock server needs a response");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State {
+            responses,
+            requests: Vec::new(),
+        }));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => break,
+                };
+                let state = shared.clone();
+                thread::spawn(move || handle(stream, &state));
+            }
+        });
+
+        Server { url, state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Request targets received so far, path and query included
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+fn handle(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    let target = line.split_whitespace().nth(1).unwrap_or("").to_string();
+
+    // Requests from the client have no body, skip to the blank line
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+    }
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(target);
+        if state.responses.len() > 1 {
+            state.responses.remove(0)
+        } else {
+            state.responses[0].clone()
+        }
+    };
+
+    thread::sleep(response.delay);
+
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let mut stream = reader.into_inner();
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(response.body.as_bytes());
+}
@@ -2,11 +2,25 @@
 
 pub mod cache;
 mod client;
+mod dividends;
+mod earnings;
+mod error;
 mod market;
+#[cfg(test)]
+mod mock;
+mod names;
+mod news;
 mod reference;
 mod stock;
+mod universe;
 
 pub use self::{
-    client::Client, market::Market, reference::Reference, reference::Symbol,
-    stock::Quote, stock::Stock,
+    client::Client, client::RetryPolicy, dividends::Dividend,
+    dividends::Dividends, dividends::Split, earnings::Earnings,
+    earnings::EarningsReport, earnings::UpcomingEarnings, error::Error,
+    error::Result, market::Market, names::NameIndex, news::Article,
+    news::News, reference::issue_type_name, reference::Reference,
+    reference::Symbol, reference::ISSUE_TYPES, stock::AdvancedStats,
+    stock::ChartPoint, stock::ChartRange, stock::Company, stock::KeyStats,
+    stock::Quote, stock::Stock, stock::UnknownRange, universe::Universe,
 };
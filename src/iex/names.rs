@@ -0,0 +1,295 @@
+//
+
+use super::reference::Symbol;
+
+use strsim::jaro_winkler;
+
+use std::{collections::HashMap, sync::Arc};
+
+/// Words in issue names that do not help tell companies apart
+const NOISE: &[&str] = &[
+    "ag",
+    "co",
+    "company",
+    "corp",
+    "corporation",
+    "group",
+    "holding",
+    "holdings",
+    "inc",
+    "incorporated",
+    "limited",
+    "llc",
+    "lp",
+    "ltd",
+    "nv",
+    "plc",
+    "sa",
+    "se",
+    "the",
+];
+
+/// Most matches returned for a single query
+const MAX_MATCHES: usize = 5;
+
+/// Shortest query that is fuzzy matched, shorter ones match too widely
+const MIN_FUZZY_LEN: usize = 4;
+
+/// Similarity a name needs to be a fuzzy match
+const FUZZY_THRESHOLD: f64 = 0.9;
+
+/// How far behind the best fuzzy match another may be and still be
+/// offered
+const FUZZY_MARGIN: f64 = 0.02;
+
+/// Reduces a company name to the words that identify it
+///
+/// IEX appends descriptions such as share classes after ` - `, those are
+/// dropped along with case, punctuation and words like `Inc`, so
+/// `Alphabet Inc - Class A` becomes `alphabet`.
+pub fn normalize_name(name: &str) -> String {
+    let name = name.split(" - ").next().unwrap_or("").to_lowercase();
+
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !NOISE.contains(w))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+struct Entry {
+    key: String,
+    symbol: Arc<Symbol>,
+}
+
+/// Listed issues indexed by company name
+#[derive(Default)]
+pub struct NameIndex {
+    entries: Vec<Entry>,
+    names: HashMap<String, Vec<usize>>,
+    first_words: HashMap<String, Vec<usize>>,
+}
+
+impl NameIndex {
+    pub fn insert<S: Into<Arc<Symbol>>>(&mut self, symbol: S) {
+        let symbol = symbol.into();
+        let key = normalize_name(&symbol.name);
+        if key.is_empty() {
+            return;
+        }
+
+        let i = self.entries.len();
+        if let Some(first) = key.split(' ').next() {
+            self.first_words
+                .entry(first.to_string())
+                .or_default()
+                .push(i);
+        }
+        self.names.entry(key.clone()).or_default().push(i);
+
+        self.entries.push(Entry { key, symbol });
+    }
+
+    /// Issues whose whole name is `query`
+    pub fn exact(&self, query: &str) -> Vec<&Symbol> {
+        match self.names.get(&normalize_name(query)) {
+            Some(found) => self.matches(found.iter().copied()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Issues best matching `query`, by whole name, then by the first word
+    /// of the name and finally by similarity
+    pub fn find(&self, query: &str) -> Vec<&Symbol> {
+        let key = normalize_name(query);
+        if key.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(found) = self.names.get(&key) {
+            return self.matches(found.iter().copied());
+        }
+
+        if let Some(found) = self.first_words.get(&key) {
+            return self.matches(found.iter().copied());
+        }
+
+        if key.len() < MIN_FUZZY_LEN {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(f64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (jaro_winkler(&key, &e.key), i))
+            .filter(|(score, _)| *score >= FUZZY_THRESHOLD)
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let best = match scored.first() {
+            Some(&(score, _)) => score,
+            None => return Vec::new(),
+        };
+
+        self.matches(
+            scored
+                .into_iter()
+                .take_while(|(score, _)| best - score <= FUZZY_MARGIN)
+                .map(|(_, i)| i),
+        )
+    }
+
+    /// Issues matching `query` by symbol or name, best first
+    ///
+    /// Symbols matching exactly or by prefix rank above names matching
+    /// exactly, by the start of a word or by similarity. Common stock
+    /// ranks above other issues that match as well.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&Symbol> {
+        let key = normalize_name(query);
+        let ticker = query.trim().trim_start_matches('$').to_uppercase();
+        if key.is_empty() && ticker.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(f64, &Symbol)> = self
+            .entries
+            .iter()
+            .filter_map(|e| {
+                let score = if e.symbol.symbol == ticker {
+                    5.0
+                } else if e.key == key {
+                    4.0
+                } else if e.symbol.symbol.starts_with(&ticker) {
+                    3.0
+                } else if !key.is_empty()
+                    && (e.key.starts_with(&key)
+                        || e.key.contains(&format!(" {}", key)))
+                {
+                    2.0
+                } else if key.len() >= MIN_FUZZY_LEN {
+                    jaro_winkler(&key, &e.key)
+                } else {
+                    0.0
+                };
+
+                if score < FUZZY_THRESHOLD {
+                    return None;
+                }
+
+                let common = if e.symbol.issue_type == "cs" {
+                    0.5
+                } else {
+                    0.0
+                };
+                Some((score + common, e.symbol.as_ref()))
+            })
+            .collect();
+
+        scored.sort_by(|a, b| {
+            b.0.partial_cmp(&a.0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.1.symbol.len().cmp(&b.1.symbol.len()))
+                .then_with(|| a.1.symbol.cmp(&b.1.symbol))
+        });
+        scored.dedup_by(|a, b| a.1.symbol == b.1.symbol);
+
+        scored.into_iter().take(limit).map(|(_, s)| s).collect()
+    }
+
+    /// Symbols for the entries, keeping only common stock when any of
+    /// them are, as the other issues are usually preferreds or notes of
+    /// the same company
+    fn matches<I>(&self, indices: I) -> Vec<&Symbol>
+    where
+        I: Iterator<Item = usize>,
+    {
+        let entries: Vec<&Symbol> =
+            indices.map(|i| self.entries[i].symbol.as_ref()).collect();
+        let common = entries.iter().any(|s| s.issue_type == "cs");
+
+        let mut matches: Vec<&Symbol> = Vec::new();
+        for symbol in entries
+            .into_iter()
+            .filter(|s| s.issue_type == "cs" || !common)
+        {
+            if matches.len() >= MAX_MATCHES {
+                break;
+            }
+
+            if !matches.iter().any(|m| m.symbol == symbol.symbol) {
+                matches.push(symbol);
+            }
+        }
+
+        matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NameIndex;
+    use crate::iex::Symbol;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const ISSUES: &[(&str, &str, &str)] = &[
+        ("AAPL", "Apple Inc", "cs"),
+        ("APLE", "Apple Hospitality REIT Inc", "cs"),
+        ("F", "Ford Motor Co.", "cs"),
+        ("F-B", "Ford Motor Co. - 6.20% NT REDEEM 01/06/2059", "ps"),
+        ("GOOGL", "Alphabet Inc - Class A", "cs"),
+        ("GOOG", "Alphabet Inc - Class C", "cs"),
+        ("NVDA", "NVIDIA Corp", "cs"),
+        ("TSLA", "Tesla Inc", "cs"),
+    ];
+
+    #[rstest(
+        query, result,
+        case::exact("apple", &["AAPL"]),
+        case::case("TESLA", &["TSLA"]),
+        case::suffix("Tesla, Inc.", &["TSLA"]),
+        case::first_word("ford", &["F"]),
+        case::several("alphabet", &["GOOGL", "GOOG"]),
+        case::fuzzy("nvidea", &["NVDA"]),
+        case::short("app", &[]),
+        case::unknown("banana", &[]),
+    )]
+    fn find(query: &str, result: &[&str]) {
+        let index = index();
+        let found: Vec<&str> = index
+            .find(query)
+            .into_iter()
+            .map(|s| s.symbol.as_str())
+            .collect();
+        assert_eq!(found, result);
+    }
+
+    #[rstest(
+        query, result,
+        case::symbol("F", &["F", "F-B"]),
+        case::symbol_prefix("goog", &["GOOG", "GOOGL"]),
+        case::name("apple", &["AAPL", "APLE"]),
+        case::word("hospitality", &["APLE"]),
+        case::fuzzy("tesle", &["TSLA"]),
+    )]
+    fn search(query: &str, result: &[&str]) {
+        let index = index();
+        let found: Vec<&str> = index
+            .search(query, 3)
+            .into_iter()
+            .map(|s| s.symbol.as_str())
+            .collect();
+        assert_eq!(found, result);
+    }
+
+    fn index() -> NameIndex {
+        let mut index = NameIndex::default();
+        for (symbol, name, issue_type) in ISSUES {
+            index.insert(Symbol::test(symbol, name, issue_type));
+        }
+        index
+    }
+}
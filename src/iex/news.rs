@@ -0,0 +1,36 @@
+//
+
+use super::client::Client;
+use super::error::Result;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Most articles IEX returns for a single request
+pub const MAX_ARTICLES: usize = 50;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Article {
+    /// Milliseconds since the epoch
+    pub datetime: i64,
+    pub headline: String,
+    pub source: String,
+    pub url: String,
+    #[serde(default)]
+    pub has_paywall: bool,
+}
+
+#[async_trait]
+pub trait News {
+    async fn news(&self, symbol: &str, last: usize) -> Result<Vec<Article>>;
+}
+
+#[async_trait]
+impl News for Client {
+    async fn news(&self, symbol: &str, last: usize) -> Result<Vec<Article>> {
+        let path =
+            format!("stock/{}/news/last/{}", symbol, last.min(MAX_ARTICLES));
+        Ok(self.get::<Vec<Article>>(&path).await?)
+    }
+}
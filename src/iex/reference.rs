@@ -1,11 +1,11 @@
 //
 
 use super::client::Client;
-use anyhow::Result;
+use super::error::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Symbol {
     pub symbol: String,
     pub exchange: String,
@@ -23,6 +23,60 @@ pub struct Symbol {
     pub cik: Option<String>,
 }
 
+/// Issue type codes IEX uses and their readable names
+pub const ISSUE_TYPES: &[(&str, &str)] = &[
+    ("ad", "ADR"),
+    ("cef", "Closed end fund"),
+    ("cs", "Common stock"),
+    ("et", "ETF"),
+    ("lp", "Limited partnership"),
+    ("oef", "Open end fund"),
+    ("ps", "Preferred stock"),
+    ("re", "REIT"),
+    ("rt", "Right"),
+    ("si", "Secondary issue"),
+    ("struct", "Structured product"),
+    ("ut", "Unit"),
+    ("wi", "When issued"),
+    ("wt", "Warrant"),
+];
+
+/// Readable name of an IEX issue type code
+pub fn issue_type_name(code: &str) -> &str {
+    if code.is_empty() {
+        return "Unknown";
+    }
+
+    ISSUE_TYPES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, name)| name)
+}
+
+impl Symbol {
+    pub fn issue_type_name(&self) -> &str {
+        issue_type_name(&self.issue_type)
+    }
+
+    /// A listing with only the fields the bot looks at filled in
+    #[cfg(test)]
+    pub fn test(symbol: &str, name: &str, issue_type: &str) -> Self {
+        Symbol {
+            symbol: symbol.to_string(),
+            exchange: "NAS".to_string(),
+            name: name.to_string(),
+            date: "2020-06-01".to_string(),
+            enabled: true,
+            issue_type: issue_type.to_string(),
+            region: "US".to_string(),
+            currency: "USD".to_string(),
+            iex_id: String::new(),
+            figi: None,
+            cik: None,
+        }
+    }
+}
+
 #[async_trait]
 pub trait Reference {
     async fn symbols(&self) -> Result<Vec<Symbol>>;
@@ -1,12 +1,18 @@
 //
 
 use super::client::Client;
-use anyhow::Result;
+use super::error::Result;
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr};
+use thiserror::Error;
+
+/// Maximum number of symbols IEX accepts in a single batch request
+const BATCH_LIMIT: usize = 100;
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-#[derive(Debug, Serialize, Deserialize)]
 pub struct Quote {
     #[serde(rename = "companyName")]
     pub name: String,
@@ -30,11 +36,177 @@ pub struct Quote {
     pub volume: Option<i64>,
     pub change: f64,
     pub change_percent: f64,
+    pub previous_close: Option<f64>,
+    pub avg_total_volume: Option<i64>,
+    pub primary_exchange: Option<String>,
+
+    pub market_cap: Option<f64>,
+    pub pe_ratio: Option<f64>,
+    pub week52_high: Option<f64>,
+    pub week52_low: Option<f64>,
+    pub ytd_change: Option<f64>,
+
+    pub extended_price: Option<f64>,
+    pub extended_change: Option<f64>,
+    pub extended_change_percent: Option<f64>,
+    pub extended_price_time: Option<i64>,
+}
+
+/// Time span covered by a chart request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartRange {
+    OneDay,
+    FiveDays,
+    OneMonth,
+    ThreeMonths,
+    SixMonths,
+    YearToDate,
+    OneYear,
+    FiveYears,
+}
+
+impl ChartRange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartRange::OneDay => "1d",
+            ChartRange::FiveDays => "5d",
+            ChartRange::OneMonth => "1m",
+            ChartRange::ThreeMonths => "3m",
+            ChartRange::SixMonths => "6m",
+            ChartRange::YearToDate => "ytd",
+            ChartRange::OneYear => "1y",
+            ChartRange::FiveYears => "5y",
+        }
+    }
+
+    /// Whether the chart points are intraday minute bars
+    pub fn is_intraday(&self) -> bool {
+        *self == ChartRange::OneDay
+    }
+}
+
+impl Default for ChartRange {
+    fn default() -> Self {
+        ChartRange::OneMonth
+    }
+}
+
+impl fmt::Display for ChartRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A chart range IEX does not offer, as given
+#[derive(Debug, Error, PartialEq)]
+#[error("unknown chart range {0:?}")]
+pub struct UnknownRange(pub String);
+
+impl FromStr for ChartRange {
+    type Err = UnknownRange;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "1d" => Ok(ChartRange::OneDay),
+            "5d" => Ok(ChartRange::FiveDays),
+            "1m" => Ok(ChartRange::OneMonth),
+            "3m" => Ok(ChartRange::ThreeMonths),
+            "6m" => Ok(ChartRange::SixMonths),
+            "ytd" => Ok(ChartRange::YearToDate),
+            "1y" => Ok(ChartRange::OneYear),
+            "5y" => Ok(ChartRange::FiveYears),
+            _ => Err(UnknownRange(s.to_string())),
+        }
+    }
+}
+
+/// A single bar of a chart, daily or (for `1d`) per minute
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChartPoint {
+    pub date: String,
+    pub minute: Option<String>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub volume: Option<i64>,
+}
+
+/// What a listed company is and does
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Company {
+    pub symbol: String,
+    #[serde(rename = "companyName")]
+    pub name: String,
+    pub exchange: Option<String>,
+    pub industry: Option<String>,
+    pub sector: Option<String>,
+    pub website: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "CEO")]
+    pub ceo: Option<String>,
+    pub employees: Option<i64>,
+    pub country: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Key figures about a listed company
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyStats {
+    pub company_name: String,
+    #[serde(rename = "marketcap")]
+    pub market_cap: Option<f64>,
+    pub shares_outstanding: Option<f64>,
+    pub float: Option<f64>,
+    pub beta: Option<f64>,
+    pub dividend_yield: Option<f64>,
+    #[serde(rename = "ttmEPS")]
+    pub ttm_eps: Option<f64>,
+    pub pe_ratio: Option<f64>,
+    pub next_earnings_date: Option<String>,
+}
+
+/// Key figures along with fundamentals from the company's financials
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdvancedStats {
+    #[serde(flatten)]
+    pub stats: KeyStats,
+    #[serde(rename = "forwardPERatio")]
+    pub forward_pe_ratio: Option<f64>,
+    pub peg_ratio: Option<f64>,
+    pub revenue: Option<f64>,
+    pub profit_margin: Option<f64>,
+    pub debt_to_equity: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Logo {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Batch {
+    quote: Quote,
 }
 
 #[async_trait]
 pub trait Stock {
     async fn quote(&self, symbol: &str) -> Result<Quote>;
+    async fn quotes(&self, symbols: &[&str])
+        -> Result<HashMap<String, Quote>>;
+    async fn chart(
+        &self,
+        symbol: &str,
+        range: ChartRange,
+    ) -> Result<Vec<ChartPoint>>;
+    async fn company(&self, symbol: &str) -> Result<Company>;
+    async fn logo(&self, symbol: &str) -> Result<String>;
+    async fn stats(&self, symbol: &str) -> Result<KeyStats>;
+    async fn advanced_stats(&self, symbol: &str) -> Result<AdvancedStats>;
 }
 
 #[async_trait]
@@ -43,4 +215,121 @@ impl Stock for Client {
         let path = format!("stock/{}/quote/", symbol);
         Ok(self.get::<Quote>(&path).await?)
     }
+
+    async fn quotes(
+        &self,
+        symbols: &[&str],
+    ) -> Result<HashMap<String, Quote>> {
+        let mut quotes = HashMap::with_capacity(symbols.len());
+
+        for chunk in symbols.chunks(BATCH_LIMIT) {
+            let symbols = chunk.join(",");
+            let batch = self
+                .get_with_query::<HashMap<String, Batch>>(
+                    "stock/market/batch",
+                    &[("symbols", &symbols), ("types", "quote")],
+                )
+                .await?;
+
+            quotes.extend(
+                batch
+                    .into_iter()
+                    .map(|(_, b)| (b.quote.symbol.clone(), b.quote)),
+            );
+        }
+
+        Ok(quotes)
+    }
+
+    async fn chart(
+        &self,
+        symbol: &str,
+        range: ChartRange,
+    ) -> Result<Vec<ChartPoint>> {
+        let path = format!("stock/{}/chart/{}", symbol, range);
+        Ok(self.get::<Vec<ChartPoint>>(&path).await?)
+    }
+
+    async fn company(&self, symbol: &str) -> Result<Company> {
+        let path = format!("stock/{}/company", symbol);
+        Ok(self.get::<Company>(&path).await?)
+    }
+
+    async fn logo(&self, symbol: &str) -> Result<String> {
+        let path = format!("stock/{}/logo", symbol);
+        Ok(self.get::<Logo>(&path).await?.url)
+    }
+
+    async fn stats(&self, symbol: &str) -> Result<KeyStats> {
+        let path = format!("stock/{}/stats", symbol);
+        Ok(self.get::<KeyStats>(&path).await?)
+    }
+
+    async fn advanced_stats(&self, symbol: &str) -> Result<AdvancedStats> {
+        let path = format!("stock/{}/advanced-stats", symbol);
+        Ok(self.get::<AdvancedStats>(&path).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChartRange, Stock, UnknownRange, BATCH_LIMIT};
+    use crate::iex::mock::{self, Response, Server};
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        input,
+        range,
+        case::one_day("1d", ChartRange::OneDay),
+        case::five_days("5d", ChartRange::FiveDays),
+        case::one_month("1m", ChartRange::OneMonth),
+        case::three_months("3m", ChartRange::ThreeMonths),
+        case::six_months("6m", ChartRange::SixMonths),
+        case::year_to_date("ytd", ChartRange::YearToDate),
+        case::one_year("1y", ChartRange::OneYear),
+        case::five_years("5y", ChartRange::FiveYears)
+    )]
+    fn chart_range(input: &str, range: ChartRange) {
+        assert_eq!(input.parse::<ChartRange>(), Ok(range));
+        assert_eq!(input.to_uppercase().parse::<ChartRange>(), Ok(range));
+        assert_eq!(range.as_str(), input);
+    }
+
+    #[rstest(input, case::unknown("2w"), case::empty(""))]
+    fn chart_range_unknown(input: &str) {
+        assert_eq!(
+            input.parse::<ChartRange>(),
+            Err(UnknownRange(input.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn quotes_batches() {
+        let symbols: Vec<String> =
+            (0..BATCH_LIMIT + 5).map(|i| format!("S{:03}", i)).collect();
+        let symbols: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+        let (first, second) = symbols.split_at(BATCH_LIMIT);
+
+        let server = Server::start(vec![
+            Response::ok(&mock::batch(first)),
+            Response::ok(&mock::batch(second)),
+        ]);
+
+        let quotes = mock::client(server.url(), 1)
+            .quotes(&symbols)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains(&first.join("%2C")), "{}", requests[0]);
+        assert!(requests[1].contains(&second.join("%2C")), "{}", requests[1]);
+
+        assert_eq!(quotes.len(), symbols.len());
+        for symbol in &symbols {
+            assert_eq!(quotes[*symbol].symbol, *symbol);
+        }
+    }
 }
@@ -0,0 +1,65 @@
+//
+
+use super::names::NameIndex;
+use super::reference::Symbol;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+/// Every listed issue, indexed by symbol and by company name
+///
+/// Built once from each download of the symbol list and shared, so looking
+/// up a symbol never copies the list.
+#[derive(Default)]
+pub struct Universe {
+    symbols: HashMap<String, Arc<Symbol>>,
+    names: NameIndex,
+    exchanges: BTreeSet<String>,
+}
+
+impl Universe {
+    /// Indexes the symbol list, only enabled issues are indexed by name
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        let mut universe = Universe::default();
+
+        for symbol in symbols {
+            let symbol = Arc::new(symbol);
+            if symbol.enabled {
+                universe.names.insert(Arc::clone(&symbol));
+            }
+            if !universe.exchanges.contains(&symbol.exchange) {
+                universe.exchanges.insert(symbol.exchange.clone());
+            }
+            universe.symbols.insert(symbol.symbol.clone(), symbol);
+        }
+
+        universe
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Symbol> {
+        self.symbols.get(symbol).map(|s| s.as_ref())
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains_key(symbol)
+    }
+
+    pub fn names(&self) -> &NameIndex {
+        &self.names
+    }
+
+    /// Codes of the exchanges issues are listed on, in order
+    pub fn exchanges(&self) -> &BTreeSet<String> {
+        &self.exchanges
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
@@ -1,8 +1,14 @@
 //
 
+mod alerts;
+mod calendar;
+mod chart;
 mod commands;
+mod filter;
 mod hooks;
 mod iex;
+mod portfolio;
+mod storage;
 mod utils;
 
 use async_trait::async_trait;
@@ -14,17 +20,11 @@ use serenity::{
         HelpOptions, StandardFramework,
     },
     http::Http,
-    model::{
-        channel::Message,
-        id::{ChannelId, UserId},
-    },
+    model::{channel::Message, id::UserId},
     model::{event::ResumedEvent, gateway::Ready},
     utils::TypeMapKey,
 };
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
 struct ShardManagerContainer;
@@ -39,10 +39,16 @@ impl TypeMapKey for IEXClient {
     type Value = iex::Client;
 }
 
-struct ChannelLastStocks;
+struct DetectionThreshold;
+
+impl TypeMapKey for DetectionThreshold {
+    type Value = f64;
+}
+
+struct Database;
 
-impl TypeMapKey for ChannelLastStocks {
-    type Value = HashMap<ChannelId, HashSet<String>>;
+impl TypeMapKey for Database {
+    type Value = Arc<storage::Storage>;
 }
 
 struct Handler;
@@ -115,10 +121,46 @@ async fn main() {
     let symbol_cache_ttl = env_default!("SYMBOL_CACHE_TTL", "604800")
         .parse::<u64>()
         .unwrap();
-    iex::cache::set_symbols_lifetime(symbol_cache_ttl).await;
+    let quote_cache_ttl =
+        env_default!("QUOTE_CACHE_TTL", "5").parse::<u64>().unwrap();
+    let quote_cache_closed_ttl = env_default!("QUOTE_CACHE_CLOSED_TTL", "300")
+        .parse::<u64>()
+        .unwrap();
+    iex::cache::set_quote_lifetimes(quote_cache_ttl, quote_cache_closed_ttl);
+    let iex_max_attempts = env_default!("IEX_MAX_ATTEMPTS", "4")
+        .parse::<u32>()
+        .unwrap();
+    let iex_max_retry_time = env_default!("IEX_MAX_RETRY_TIME", "30")
+        .parse::<u64>()
+        .unwrap();
+
+    let data_dir = PathBuf::from(env_default!("DATA_DIR", "."));
+    std::fs::create_dir_all(&data_dir).expect("Unable to create DATA_DIR");
+    let database_path = match std::env::var("DATABASE_PATH") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => data_dir.join("dobbybot.sqlite3"),
+    };
+    let database = storage::Storage::open(&database_path)
+        .expect("Unable to open database");
+    let detection_threshold =
+        env_default!("DETECTION_THRESHOLD", utils::DEFAULT_THRESHOLD)
+            .parse::<f64>()
+            .unwrap();
+    let alert_interval =
+        env_default!("ALERT_INTERVAL", "60").parse::<u64>().unwrap();
 
     let http = Http::new_with_token(&discord_token);
-    let iex_client = iex::Client::new(iex_token);
+    let mut iex_client =
+        iex::Client::new(iex_token).with_retry_policy(iex::RetryPolicy {
+            max_attempts: iex_max_attempts,
+            max_elapsed: Duration::from_secs(iex_max_retry_time),
+            ..Default::default()
+        });
+    if let Ok(base_url) = std::env::var("IEX_BASE_URL") {
+        iex_client = iex_client
+            .with_base_url(&base_url)
+            .expect("Unable to parse IEX_BASE_URL");
+    }
 
     let owners = match http.get_current_application_info().await {
         Ok(info) => {
@@ -149,10 +191,22 @@ async fn main() {
         data.insert::<ShardManagerContainer>(Arc::clone(
             &client.shard_manager,
         ));
-        data.insert::<IEXClient>(iex_client);
-        data.insert::<ChannelLastStocks>(HashMap::new());
+        data.insert::<IEXClient>(iex_client.clone());
+        data.insert::<DetectionThreshold>(detection_threshold);
+        data.insert::<Database>(Arc::new(database));
     }
 
+    tokio::spawn(iex::cache::refresh(
+        iex_client,
+        Duration::from_secs(symbol_cache_ttl),
+    ));
+
+    tokio::spawn(alerts::poll(
+        Arc::clone(&client.cache_and_http.http),
+        Arc::clone(&client.data),
+        Duration::from_secs(alert_interval),
+    ));
+
     if let Err(why) = client.start().await {
         error!("Client error: {:?}", why);
     }
@@ -0,0 +1,284 @@
+//
+
+use serenity::model::id::{GuildId, UserId};
+use thiserror::Error;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Cash every portfolio is opened with
+pub const STARTING_CASH: f64 = 100_000.0;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum TradeError {
+    #[error("Trades need at least one share")]
+    NoShares,
+    #[error("No usable price for {symbol}, try again later")]
+    InvalidPrice { symbol: String },
+    #[error("That is more shares of {symbol} than can be held")]
+    TooManyShares { symbol: String },
+    #[error("Not enough cash, that costs {cost:.2} but you have {cash:.2}")]
+    InsufficientCash { cost: f64, cash: f64 },
+    #[error("You only hold {held} shares of {symbol}")]
+    InsufficientShares { symbol: String, held: u64 },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Position {
+    pub shares: u64,
+    /// Total paid for the shares still held
+    pub cost: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Portfolio {
+    pub user: UserId,
+    pub guild: Option<GuildId>,
+    pub cash: f64,
+    pub positions: BTreeMap<String, Position>,
+}
+
+/// Rejects prices a trade cannot be filled at, such as the zero IEX
+/// reports for a listing that has not traded
+fn check_price(symbol: &str, price: f64) -> Result<(), TradeError> {
+    if price.is_finite() && price > 0.0 {
+        Ok(())
+    } else {
+        Err(TradeError::InvalidPrice {
+            symbol: symbol.to_string(),
+        })
+    }
+}
+
+impl Portfolio {
+    pub fn new(guild: Option<GuildId>, user: UserId) -> Self {
+        Portfolio {
+            user,
+            guild,
+            cash: STARTING_CASH,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    pub fn buy(
+        &mut self,
+        symbol: &str,
+        shares: u64,
+        price: f64,
+    ) -> Result<(), TradeError> {
+        if shares == 0 {
+            return Err(TradeError::NoShares);
+        }
+        check_price(symbol, price)?;
+
+        let cost = shares as f64 * price;
+        if cost > self.cash {
+            return Err(TradeError::InsufficientCash {
+                cost,
+                cash: self.cash,
+            });
+        }
+
+        let held = self.positions.get(symbol).map_or(0, |p| p.shares);
+        let total = held.checked_add(shares).ok_or_else(|| {
+            TradeError::TooManyShares {
+                symbol: symbol.to_string(),
+            }
+        })?;
+
+        let position = self.positions.entry(symbol.to_string()).or_default();
+        position.shares = total;
+        position.cost += cost;
+        self.cash -= cost;
+
+        Ok(())
+    }
+
+    /// Sells shares at `price`, returning the realized profit or loss
+    pub fn sell(
+        &mut self,
+        symbol: &str,
+        shares: u64,
+        price: f64,
+    ) -> Result<f64, TradeError> {
+        if shares == 0 {
+            return Err(TradeError::NoShares);
+        }
+        check_price(symbol, price)?;
+
+        let held = self.positions.get(symbol).map_or(0, |p| p.shares);
+        if shares > held {
+            return Err(TradeError::InsufficientShares {
+                symbol: symbol.to_string(),
+                held,
+            });
+        }
+
+        let position = self.positions.get_mut(symbol).unwrap();
+        let basis = position.cost * shares as f64 / position.shares as f64;
+        let proceeds = shares as f64 * price;
+
+        position.shares -= shares;
+        position.cost -= basis;
+        if position.shares == 0 {
+            self.positions.remove(symbol);
+        }
+        self.cash += proceeds;
+
+        Ok(proceeds - basis)
+    }
+
+    /// Cash plus the holdings at the given prices, falling back to cost
+    /// basis for anything without a price
+    pub fn value(&self, prices: &HashMap<String, f64>) -> f64 {
+        self.cash
+            + self
+                .positions
+                .iter()
+                .map(|(s, p)| match prices.get(s) {
+                    Some(price) => p.shares as f64 * price,
+                    None => p.cost,
+                })
+                .sum::<f64>()
+    }
+
+    /// Return on the starting cash, as a fraction
+    pub fn total_return(&self, prices: &HashMap<String, f64>) -> f64 {
+        self.value(prices) / STARTING_CASH - 1.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Portfolio, Position, TradeError, STARTING_CASH};
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serenity::model::id::UserId;
+    use std::collections::HashMap;
+
+    fn portfolio() -> Portfolio {
+        Portfolio::new(None, UserId(1))
+    }
+
+    #[test]
+    fn buy() {
+        let mut p = portfolio();
+
+        assert_eq!(p.buy("AAPL", 10, 100.0), Ok(()));
+        assert_eq!(p.buy("AAPL", 10, 200.0), Ok(()));
+
+        assert_eq!(p.cash, STARTING_CASH - 3000.0);
+        assert_eq!(
+            p.positions["AAPL"],
+            Position {
+                shares: 20,
+                cost: 3000.0
+            }
+        );
+    }
+
+    #[test]
+    fn buy_insufficient_cash() {
+        let mut p = portfolio();
+
+        assert_eq!(
+            p.buy("AAPL", 1001, 100.0),
+            Err(TradeError::InsufficientCash {
+                cost: 100_100.0,
+                cash: STARTING_CASH
+            })
+        );
+        assert!(p.positions.is_empty());
+    }
+
+    #[rstest(
+        price,
+        case::zero(0.0),
+        case::negative(-1.0),
+        case::nan(f64::NAN),
+        case::infinite(f64::INFINITY)
+    )]
+    fn invalid_price(price: f64) {
+        let mut p = portfolio();
+        p.buy("AAPL", 1, 100.0).unwrap();
+        let before = p.clone();
+
+        let invalid = TradeError::InvalidPrice {
+            symbol: "AAPL".to_string(),
+        };
+        assert_eq!(p.buy("AAPL", 1, price), Err(invalid.clone()));
+        assert_eq!(p.sell("AAPL", 1, price), Err(invalid));
+
+        assert_eq!(p.cash, before.cash);
+        assert_eq!(p.positions, before.positions);
+    }
+
+    #[test]
+    fn buy_too_many_shares() {
+        let mut p = portfolio();
+        p.positions.insert(
+            "AAPL".to_string(),
+            Position {
+                shares: u64::MAX,
+                cost: 1.0,
+            },
+        );
+
+        assert_eq!(
+            p.buy("AAPL", 1, 1.0),
+            Err(TradeError::TooManyShares {
+                symbol: "AAPL".to_string()
+            })
+        );
+        assert_eq!(p.positions["AAPL"].shares, u64::MAX);
+        assert_eq!(p.cash, STARTING_CASH);
+    }
+
+    #[test]
+    fn sell() {
+        let mut p = portfolio();
+        p.buy("AAPL", 10, 100.0).unwrap();
+
+        assert_eq!(p.sell("AAPL", 4, 150.0), Ok(200.0));
+        assert_eq!(
+            p.positions["AAPL"],
+            Position {
+                shares: 6,
+                cost: 600.0
+            }
+        );
+
+        assert_eq!(p.sell("AAPL", 6, 50.0), Ok(-300.0));
+        assert!(p.positions.is_empty());
+        assert_eq!(p.cash, STARTING_CASH - 100.0);
+    }
+
+    #[test]
+    fn sell_insufficient_shares() {
+        let mut p = portfolio();
+        p.buy("AAPL", 1, 100.0).unwrap();
+
+        assert_eq!(
+            p.sell("AAPL", 2, 100.0),
+            Err(TradeError::InsufficientShares {
+                symbol: "AAPL".to_string(),
+                held: 1
+            })
+        );
+        assert_eq!(p.sell("TSLA", 0, 100.0), Err(TradeError::NoShares));
+    }
+
+    #[test]
+    fn total_return() {
+        let mut p = portfolio();
+        p.buy("AAPL", 100, 100.0).unwrap();
+        p.buy("TSLA", 100, 100.0).unwrap();
+
+        let mut prices = HashMap::new();
+        prices.insert("AAPL".to_string(), 200.0);
+
+        // TSLA has no price so is valued at cost
+        assert_eq!(p.value(&prices), STARTING_CASH + 10_000.0);
+        assert!((p.total_return(&prices) - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,223 @@
+//
+
+use super::{guild_key, Storage};
+
+use crate::alerts::{Alert, Condition};
+
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension, Row, NO_PARAMS};
+use serenity::model::id::{ChannelId, GuildId, UserId};
+
+use std::collections::HashMap;
+
+const COLUMNS: &str =
+    "id, user_id, guild_id, channel_id, symbol, kind, value, met";
+
+/// Maps a row selected with `COLUMNS`, skipping unknown condition kinds
+fn alert(row: &Row) -> rusqlite::Result<Option<Alert>> {
+    let kind: String = row.get(5)?;
+    let condition = match Condition::from_parts(&kind, row.get(6)?) {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    let guild: i64 = row.get(2)?;
+    let channel: Option<i64> = row.get(3)?;
+
+    Ok(Some(Alert {
+        id: row.get::<_, i64>(0)? as u64,
+        user: UserId(row.get::<_, i64>(1)? as u64),
+        guild: if guild == 0 {
+            None
+        } else {
+            Some(GuildId(guild as u64))
+        },
+        channel: channel.map(|c| ChannelId(c as u64)),
+        symbol: row.get(4)?,
+        condition,
+        met: row.get(7)?,
+    }))
+}
+
+impl Storage {
+    /// Arms a new alert, returning its id
+    ///
+    /// `met` is whether the condition holds at the current price, if that
+    /// is known.
+    pub fn add_alert(
+        &self,
+        user: UserId,
+        guild: Option<GuildId>,
+        channel: Option<ChannelId>,
+        symbol: &str,
+        condition: Condition,
+        met: Option<bool>,
+    ) -> Result<u64> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO alerts
+                 (user_id, guild_id, channel_id, symbol, kind, value, met)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    user.0 as i64,
+                    guild_key(guild),
+                    channel.map(|c| c.0 as i64),
+                    symbol,
+                    condition.kind(),
+                    condition.value(),
+                    met,
+                ],
+            )?;
+            Ok(conn.last_insert_rowid() as u64)
+        })
+    }
+
+    pub fn alerts_for_user(&self, user: UserId) -> Result<Vec<Alert>> {
+        self.with(|conn| {
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT {} FROM alerts WHERE user_id = ?1 ORDER BY id",
+                COLUMNS
+            ))?;
+            let rows = stmt.query_map(params![user.0 as i64], alert)?;
+            rows.filter_map(|r| r.transpose()).collect()
+        })
+    }
+
+    /// Removes one of the user's alerts
+    pub fn cancel_alert(
+        &self,
+        user: UserId,
+        id: u64,
+    ) -> Result<Option<Alert>> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            let found = tx
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM alerts WHERE id = ?1 AND user_id = ?2",
+                        COLUMNS
+                    ),
+                    params![id as i64, user.0 as i64],
+                    alert,
+                )
+                .optional()?
+                .flatten();
+
+            tx.execute(
+                "DELETE FROM alerts WHERE id = ?1 AND user_id = ?2",
+                params![id as i64, user.0 as i64],
+            )?;
+            tx.commit()?;
+            Ok(found)
+        })
+    }
+
+    /// Symbols that need a quote to evaluate the armed alerts
+    pub fn alert_symbols(&self) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT DISTINCT symbol FROM alerts ORDER BY symbol",
+            )?;
+            let rows = stmt.query_map(NO_PARAMS, |r| r.get(0))?;
+            rows.collect()
+        })
+    }
+
+    /// Removes and returns every alert whose condition has started to
+    /// hold, together with the price that met it
+    ///
+    /// Every other alert remembers whether its condition held, so it fires
+    /// once the price crosses its threshold.
+    pub fn take_triggered_alerts(
+        &self,
+        prices: &HashMap<String, (f64, f64)>,
+    ) -> Result<Vec<(Alert, f64)>> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+
+            let alerts: Vec<Alert> = {
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT {} FROM alerts ORDER BY id",
+                    COLUMNS
+                ))?;
+                let rows = stmt.query_map(NO_PARAMS, alert)?;
+                rows.filter_map(|r| r.transpose())
+                    .collect::<rusqlite::Result<_>>()?
+            };
+
+            let mut triggered = Vec::new();
+            for a in alerts {
+                let (price, change) = match prices.get(&a.symbol) {
+                    Some(&p) => p,
+                    None => continue,
+                };
+
+                let met = a.condition.is_met(price, change);
+                if a.fires(met) {
+                    tx.execute(
+                        "DELETE FROM alerts WHERE id = ?1",
+                        params![a.id as i64],
+                    )?;
+                    triggered.push((a, price));
+                } else if a.met != Some(met) {
+                    tx.execute(
+                        "UPDATE alerts SET met = ?2 WHERE id = ?1",
+                        params![a.id as i64, met],
+                    )?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(triggered)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storage;
+    use crate::alerts::Condition;
+
+    use pretty_assertions::assert_eq;
+    use serenity::model::id::UserId;
+    use std::collections::HashMap;
+
+    fn prices(price: f64) -> HashMap<String, (f64, f64)> {
+        vec![("AAPL".to_string(), (price, 0.0))]
+            .into_iter()
+            .collect()
+    }
+
+    fn fired(storage: &Storage, price: f64) -> Vec<u64> {
+        storage
+            .take_triggered_alerts(&prices(price))
+            .unwrap()
+            .into_iter()
+            .map(|(a, _)| a.id)
+            .collect()
+    }
+
+    #[test]
+    fn crossing() {
+        let storage = Storage::in_memory().unwrap();
+        let above = Condition::Above(200.0);
+        let add = |met| {
+            storage
+                .add_alert(UserId(1), None, None, "AAPL", above, met)
+                .unwrap()
+        };
+
+        let below = add(Some(false));
+        let past = add(Some(true));
+        let unknown = add(None);
+
+        // Set while already past the threshold, nothing fires
+        assert_eq!(fired(&storage, 210.0), vec![below]);
+        assert_eq!(fired(&storage, 210.0), Vec::<u64>::new());
+
+        // Until the price crosses back and again
+        assert_eq!(fired(&storage, 190.0), Vec::<u64>::new());
+        assert_eq!(fired(&storage, 205.0), vec![past, unknown]);
+        assert!(storage.alerts_for_user(UserId(1)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+//
+
+use super::Storage;
+
+use anyhow::Result;
+use rusqlite::params;
+use serenity::model::id::ChannelId;
+
+use std::collections::HashSet;
+
+impl Storage {
+    /// Stocks most recently mentioned in the channel
+    pub fn last_stocks(&self, channel: ChannelId) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT symbol FROM channel_last_stocks
+                 WHERE channel_id = ?1 ORDER BY symbol",
+            )?;
+            let rows =
+                stmt.query_map(params![channel.0 as i64], |r| r.get(0))?;
+            rows.collect()
+        })
+    }
+
+    /// Replaces the stocks last mentioned in the channel
+    ///
+    /// Nothing is written if they are the same as before, which is the
+    /// case for most messages.
+    pub fn set_last_stocks(
+        &self,
+        channel: ChannelId,
+        stocks: &HashSet<String>,
+    ) -> Result<()> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+
+            let current = {
+                let mut stmt = tx.prepare_cached(
+                    "SELECT symbol FROM channel_last_stocks
+                     WHERE channel_id = ?1",
+                )?;
+                let rows =
+                    stmt.query_map(params![channel.0 as i64], |r| r.get(0))?;
+                rows.collect::<rusqlite::Result<HashSet<String>>>()?
+            };
+            if &current == stocks {
+                return Ok(());
+            }
+
+            tx.execute(
+                "DELETE FROM channel_last_stocks WHERE channel_id = ?1",
+                params![channel.0 as i64],
+            )?;
+            for symbol in stocks {
+                tx.execute(
+                    "INSERT INTO channel_last_stocks (channel_id, symbol)
+                     VALUES (?1, ?2)",
+                    params![channel.0 as i64, symbol],
+                )?;
+            }
+            tx.commit()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storage;
+
+    use pretty_assertions::assert_eq;
+    use serenity::model::id::ChannelId;
+    use std::collections::HashSet;
+
+    #[test]
+    fn last_stocks() {
+        let storage = Storage::in_memory().unwrap();
+        let channel = ChannelId(1);
+        let stocks: HashSet<String> =
+            vec!["AAPL".to_string(), "TSLA".to_string()]
+                .into_iter()
+                .collect();
+
+        storage.set_last_stocks(channel, &stocks).unwrap();
+        storage.set_last_stocks(channel, &stocks).unwrap();
+        assert_eq!(
+            storage.last_stocks(channel).unwrap(),
+            vec!["AAPL", "TSLA"]
+        );
+
+        storage.set_last_stocks(channel, &HashSet::new()).unwrap();
+        assert!(storage.last_stocks(channel).unwrap().is_empty());
+    }
+}
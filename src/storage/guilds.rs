@@ -0,0 +1,146 @@
+//
+
+use super::Storage;
+
+use crate::filter::ListingFilter;
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serenity::model::id::GuildId;
+
+const ISSUE_TYPES: &str = "issue_types";
+const EXCHANGES: &str = "exchanges";
+
+fn set(
+    conn: &Connection,
+    guild: GuildId,
+    key: &str,
+    value: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO guild_settings (guild_id, key, value)
+         VALUES (?1, ?2, ?3)",
+        params![guild.0 as i64, key, value],
+    )?;
+    Ok(())
+}
+
+fn remove(
+    conn: &Connection,
+    guild: GuildId,
+    key: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM guild_settings WHERE guild_id = ?1 AND key = ?2",
+        params![guild.0 as i64, key],
+    )?;
+    Ok(())
+}
+
+fn split(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .collect()
+}
+
+impl Storage {
+    pub fn guild_setting(
+        &self,
+        guild: GuildId,
+        key: &str,
+    ) -> Result<Option<String>> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT value FROM guild_settings
+                 WHERE guild_id = ?1 AND key = ?2",
+                params![guild.0 as i64, key],
+                |r| r.get(0),
+            )
+            .optional()
+        })
+    }
+
+    /// The listings eligible in the guild, DMs always use the default
+    pub fn listing_filter(
+        &self,
+        guild: Option<GuildId>,
+    ) -> Result<ListingFilter> {
+        let mut filter = ListingFilter::default();
+
+        let guild = match guild {
+            Some(g) => g,
+            None => return Ok(filter),
+        };
+
+        if let Some(types) = self.guild_setting(guild, ISSUE_TYPES)? {
+            filter.issue_types = split(&types);
+        }
+        if let Some(exchanges) = self.guild_setting(guild, EXCHANGES)? {
+            filter.exchanges = split(&exchanges);
+        }
+
+        Ok(filter)
+    }
+
+    /// Saves both parts of the filter together, or neither
+    pub fn set_listing_filter(
+        &self,
+        guild: GuildId,
+        filter: &ListingFilter,
+    ) -> Result<()> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            set(&tx, guild, ISSUE_TYPES, &filter.issue_types.join(","))?;
+            set(&tx, guild, EXCHANGES, &filter.exchanges.join(","))?;
+            tx.commit()
+        })
+    }
+
+    pub fn reset_listing_filter(&self, guild: GuildId) -> Result<()> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            remove(&tx, guild, ISSUE_TYPES)?;
+            remove(&tx, guild, EXCHANGES)?;
+            tx.commit()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storage;
+    use crate::filter::ListingFilter;
+
+    use pretty_assertions::assert_eq;
+    use serenity::model::id::GuildId;
+
+    #[test]
+    fn listing_filter() {
+        let storage = Storage::in_memory().unwrap();
+        let filter = ListingFilter {
+            issue_types: vec!["cs".to_string(), "ad".to_string()],
+            exchanges: vec!["NYS".to_string()],
+        };
+
+        storage.set_listing_filter(GuildId(1), &filter).unwrap();
+        assert_eq!(storage.listing_filter(Some(GuildId(1))).unwrap(), filter);
+
+        // Other guilds and DMs keep the default
+        assert_eq!(
+            storage.listing_filter(Some(GuildId(2))).unwrap(),
+            ListingFilter::default()
+        );
+        assert_eq!(
+            storage.listing_filter(None).unwrap(),
+            ListingFilter::default()
+        );
+
+        storage.reset_listing_filter(GuildId(1)).unwrap();
+        assert_eq!(
+            storage.listing_filter(Some(GuildId(1))).unwrap(),
+            ListingFilter::default()
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//
+
+use rusqlite::{Connection, Result, NO_PARAMS};
+
+use log::info;
+
+/// Schema changes, applied in order
+///
+/// The database's `user_version` records how many have been applied, so
+/// entries must never be edited or reordered once released, only
+/// appended.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    r#"
+    CREATE TABLE channel_last_stocks (
+        channel_id INTEGER NOT NULL,
+        symbol TEXT NOT NULL,
+        PRIMARY KEY (channel_id, symbol)
+    );
+
+    CREATE TABLE guild_settings (
+        guild_id INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (guild_id, key)
+    );
+
+    CREATE TABLE watchlists (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        symbol TEXT NOT NULL,
+        PRIMARY KEY (guild_id, user_id, symbol)
+    );
+
+    CREATE TABLE alerts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER,
+        symbol TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        value REAL NOT NULL
+    );
+    CREATE INDEX alerts_user ON alerts (user_id);
+
+    CREATE TABLE portfolios (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        cash REAL NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    );
+
+    CREATE TABLE positions (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        symbol TEXT NOT NULL,
+        shares INTEGER NOT NULL,
+        cost REAL NOT NULL,
+        PRIMARY KEY (guild_id, user_id, symbol),
+        FOREIGN KEY (guild_id, user_id)
+            REFERENCES portfolios (guild_id, user_id) ON DELETE CASCADE
+    );
+    "#,
+    // 2: whether each alert's condition held when it was last evaluated
+    r#"
+    ALTER TABLE alerts ADD COLUMN met INTEGER;
+    "#,
+];
+
+/// Applies every migration the database has not seen yet
+pub fn run(conn: &mut Connection) -> Result<()> {
+    let version: i64 =
+        conn.query_row("PRAGMA user_version", NO_PARAMS, |r| r.get(0))?;
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", &(i as i64 + 1))?;
+        tx.commit()?;
+
+        info!("Applied storage migration {}", i + 1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//
+
+mod alerts;
+mod channels;
+mod guilds;
+mod migrations;
+mod portfolios;
+mod watchlists;
+
+pub use self::watchlists::MAX_WATCHLIST;
+
+use anyhow::{anyhow, Result};
+use rusqlite::Connection;
+use serenity::model::id::GuildId;
+
+use log::info;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::task;
+
+/// Bot state persisted in an embedded SQLite database
+///
+/// The schema is brought up to date by `migrations` when opened.
+pub struct Storage {
+    conn: Mutex<Connection>,
+}
+
+/// Runs `f` on the blocking thread pool, so waiting on SQLite does not
+/// stall the async runtime
+pub async fn blocking<T, F>(storage: Arc<Storage>, f: F) -> Result<T>
+where
+    F: FnOnce(&Storage) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    task::spawn_blocking(move || f(&storage)).await?
+}
+
+/// Guild column value for rows that belong to DMs
+fn guild_key(guild: Option<GuildId>) -> i64 {
+    guild.map_or(0, |g| g.0 as i64)
+}
+
+impl Storage {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let conn = Connection::open(path.as_ref())?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+
+        info!("Opened storage at {}", path.as_ref().display());
+        Self::with_connection(conn)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        migrations::run(&mut conn)?;
+
+        Ok(Storage {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Runs `f` with exclusive use of the connection
+    fn with<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T>,
+    {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow!("Storage connection poisoned"))?;
+        Ok(f(&mut conn)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storage;
+
+    use pretty_assertions::assert_eq;
+    use serenity::model::id::UserId;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn blocking() {
+        let storage = Arc::new(Storage::in_memory().unwrap());
+        let symbols = vec!["AAPL".to_string()];
+
+        let added = super::blocking(storage.clone(), move |db| {
+            db.watch(None, UserId(1), &symbols)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(added, vec!["AAPL"]);
+        assert_eq!(storage.watchlist(None, UserId(1)).unwrap(), vec!["AAPL"]);
+    }
+}
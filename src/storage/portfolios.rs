@@ -0,0 +1,177 @@
+//
+
+use super::{guild_key, Storage};
+
+use crate::portfolio::{Portfolio, Position};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serenity::model::id::{GuildId, UserId};
+
+fn positions(
+    conn: &Connection,
+    portfolio: &mut Portfolio,
+) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT symbol, shares, cost FROM positions
+         WHERE guild_id = ?1 AND user_id = ?2",
+    )?;
+    let rows = stmt.query_map(
+        params![guild_key(portfolio.guild), portfolio.user.0 as i64],
+        |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                Position {
+                    shares: r.get::<_, i64>(1)? as u64,
+                    cost: r.get(2)?,
+                },
+            ))
+        },
+    )?;
+
+    for row in rows {
+        let (symbol, position) = row?;
+        portfolio.positions.insert(symbol, position);
+    }
+
+    Ok(())
+}
+
+fn load(
+    conn: &Connection,
+    guild: Option<GuildId>,
+    user: UserId,
+) -> rusqlite::Result<Portfolio> {
+    let mut portfolio = Portfolio::new(guild, user);
+
+    let cash: Option<f64> = conn
+        .query_row(
+            "SELECT cash FROM portfolios WHERE guild_id = ?1 AND user_id = ?2",
+            params![guild_key(guild), user.0 as i64],
+            |r| r.get(0),
+        )
+        .optional()?;
+
+    if let Some(cash) = cash {
+        portfolio.cash = cash;
+        positions(conn, &mut portfolio)?;
+    }
+
+    Ok(portfolio)
+}
+
+fn save(conn: &Connection, portfolio: &Portfolio) -> rusqlite::Result<()> {
+    let guild = guild_key(portfolio.guild);
+    let user = portfolio.user.0 as i64;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO portfolios (guild_id, user_id, cash)
+         VALUES (?1, ?2, ?3)",
+        params![guild, user, portfolio.cash],
+    )?;
+    conn.execute(
+        "DELETE FROM positions WHERE guild_id = ?1 AND user_id = ?2",
+        params![guild, user],
+    )?;
+
+    for (symbol, position) in &portfolio.positions {
+        conn.execute(
+            "INSERT INTO positions (guild_id, user_id, symbol, shares, cost)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                guild,
+                user,
+                symbol,
+                position.shares as i64,
+                position.cost
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Storage {
+    /// The user's portfolio, or a fresh one if they have not traded yet
+    pub fn portfolio(
+        &self,
+        guild: Option<GuildId>,
+        user: UserId,
+    ) -> Result<Portfolio> {
+        self.with(|conn| load(conn, guild, user))
+    }
+
+    /// Applies `f` to the user's portfolio in a single transaction,
+    /// saving it only if `f` succeeds
+    pub fn update_portfolio<T, E, F>(
+        &self,
+        guild: Option<GuildId>,
+        user: UserId,
+        f: F,
+    ) -> Result<Result<T, E>>
+    where
+        F: FnOnce(&mut Portfolio) -> Result<T, E>,
+    {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            let mut portfolio = load(&tx, guild, user)?;
+
+            let result = f(&mut portfolio);
+            if result.is_ok() {
+                save(&tx, &portfolio)?;
+                tx.commit()?;
+            }
+
+            Ok(result)
+        })
+    }
+
+    /// Every portfolio opened in the guild
+    pub fn guild_portfolios(&self, guild: GuildId) -> Result<Vec<Portfolio>> {
+        self.with(|conn| {
+            let mut portfolios = {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT user_id, cash FROM portfolios WHERE guild_id = ?1",
+                )?;
+                let rows = stmt.query_map(params![guild.0 as i64], |r| {
+                    let mut p = Portfolio::new(
+                        Some(guild),
+                        UserId(r.get::<_, i64>(0)? as u64),
+                    );
+                    p.cash = r.get(1)?;
+                    Ok(p)
+                })?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            };
+
+            for portfolio in &mut portfolios {
+                positions(conn, portfolio)?;
+            }
+
+            Ok(portfolios)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storage;
+
+    use serenity::model::id::UserId;
+
+    #[test]
+    fn portfolio_rolls_back() {
+        let storage = Storage::in_memory().unwrap();
+
+        let result = storage
+            .update_portfolio(None, UserId(1), |p| {
+                p.buy("AAPL", 10, 100.0)?;
+                p.sell("AAPL", 20, 100.0)
+            })
+            .unwrap();
+        assert!(result.is_err());
+
+        let portfolio = storage.portfolio(None, UserId(1)).unwrap();
+        assert!(portfolio.positions.is_empty());
+    }
+}
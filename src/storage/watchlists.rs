@@ -0,0 +1,135 @@
+//
+
+use super::{guild_key, Storage};
+
+use anyhow::Result;
+use rusqlite::params;
+use serenity::model::id::{GuildId, UserId};
+
+/// Discord embeds hold at most 25 fields, one per quote
+pub const MAX_WATCHLIST: usize = 25;
+
+impl Storage {
+    pub fn watchlist(
+        &self,
+        guild: Option<GuildId>,
+        user: UserId,
+    ) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT symbol FROM watchlists
+                 WHERE guild_id = ?1 AND user_id = ?2 ORDER BY symbol",
+            )?;
+            let rows = stmt
+                .query_map(params![guild_key(guild), user.0 as i64], |r| {
+                    r.get(0)
+                })?;
+            rows.collect()
+        })
+    }
+
+    /// Symbols on anyone's watchlist in the guild
+    pub fn watched_symbols(&self, guild: GuildId) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT DISTINCT symbol FROM watchlists
+                 WHERE guild_id = ?1 ORDER BY symbol",
+            )?;
+            let rows =
+                stmt.query_map(params![guild.0 as i64], |r| r.get(0))?;
+            rows.collect()
+        })
+    }
+
+    /// Adds the symbols to the list, returning the ones that were not
+    /// already on it
+    ///
+    /// Symbols beyond `MAX_WATCHLIST` are not added.
+    pub fn watch(
+        &self,
+        guild: Option<GuildId>,
+        user: UserId,
+        symbols: &[String],
+    ) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            let mut added = Vec::new();
+
+            let mut count: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM watchlists
+                 WHERE guild_id = ?1 AND user_id = ?2",
+                params![guild_key(guild), user.0 as i64],
+                |r| r.get(0),
+            )?;
+
+            for symbol in symbols {
+                if count as usize >= MAX_WATCHLIST {
+                    break;
+                }
+
+                let inserted = tx.execute(
+                    "INSERT OR IGNORE INTO watchlists (guild_id, user_id, symbol)
+                     VALUES (?1, ?2, ?3)",
+                    params![guild_key(guild), user.0 as i64, symbol],
+                )?;
+
+                if inserted > 0 {
+                    count += 1;
+                    added.push(symbol.clone());
+                }
+            }
+
+            tx.commit()?;
+            Ok(added)
+        })
+    }
+
+    /// Removes the symbols from the list, returning the ones that were on
+    /// it
+    pub fn unwatch(
+        &self,
+        guild: Option<GuildId>,
+        user: UserId,
+        symbols: &[String],
+    ) -> Result<Vec<String>> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            let mut removed = Vec::new();
+
+            for symbol in symbols {
+                let deleted = tx.execute(
+                    "DELETE FROM watchlists
+                     WHERE guild_id = ?1 AND user_id = ?2 AND symbol = ?3",
+                    params![guild_key(guild), user.0 as i64, symbol],
+                )?;
+
+                if deleted > 0 {
+                    removed.push(symbol.clone());
+                }
+            }
+
+            tx.commit()?;
+            Ok(removed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Storage, MAX_WATCHLIST};
+
+    use pretty_assertions::assert_eq;
+    use serenity::model::id::{GuildId, UserId};
+
+    #[test]
+    fn watchlist_limit() {
+        let storage = Storage::in_memory().unwrap();
+        let guild = Some(GuildId(1));
+        let symbols: Vec<String> =
+            (0..30).map(|i| format!("S{}", i)).collect();
+
+        let added = storage.watch(guild, UserId(1), &symbols).unwrap();
+        assert_eq!(added.len(), MAX_WATCHLIST);
+        assert!(storage.watchlist(None, UserId(1)).unwrap().is_empty());
+    }
+}
@@ -1,67 +1,275 @@
 //
 
+use crate::filter::ListingFilter;
 use crate::iex;
-use crate::IEXClient;
+use crate::iex::{NameIndex, Universe};
+use crate::storage::{self, Storage};
+use crate::Database;
+use crate::DetectionThreshold;
 
 use anyhow::Result;
 use serenity::{client::Context, model::channel::Message};
 
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
 
 const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
+/// Characters users put between a ticker and its share class, as in
+/// `BRK.B`, `BRK-B` or `BRK/B`
+const CLASS_SEPARATORS: &str = ".-/";
+
+/// Score an untagged word needs to be taken as a stock
+pub const DEFAULT_THRESHOLD: f64 = 1.0;
+
+/// Tickers that are also everyday words, which need more evidence than
+/// matching a symbol before they are taken as a stock
+const STOPLIST: &[&str] = &[
+    "A", "AI", "ALL", "AM", "AN", "ANY", "ARE", "AT", "BE", "BIG", "BY",
+    "CAN", "CAR", "CASH", "DD", "DO", "EAT", "EDIT", "EVER", "FAST", "FOR",
+    "FUN", "GO", "GOOD", "HAS", "HE", "HOME", "HOPE", "I", "IS", "IT", "JOB",
+    "KEY", "LIFE", "LIVE", "LOVE", "LOW", "MAIN", "MAN", "ME", "MORE", "MOST",
+    "NEW", "NICE", "NO", "NOW", "OK", "ON", "ONE", "OPEN", "OR", "OUT",
+    "PLAY", "PLUS", "POST", "REAL", "RUN", "SAFE", "SEE", "SO", "TELL", "TWO",
+    "UP", "VERY", "WELL", "WORK", "YOU",
+];
+
+/// Words that suggest a nearby word is being used as a ticker
+const FINANCE_WORDS: &[&str] = &[
+    "bag", "bearish", "bought", "bullish", "buy", "buying", "call", "calls",
+    "dip", "dividend", "earnings", "hold", "holding", "long", "option",
+    "options", "position", "price", "put", "puts", "rally", "sell", "selling",
+    "share", "shares", "short", "shorting", "sold", "squeeze", "stock",
+    "stocks", "ticker", "yolo",
+];
+
+/// How many words either side of a candidate are searched for
+/// `FINANCE_WORDS`
+const FINANCE_WINDOW: usize = 3;
+
+/// Confidence in a capitalized company name before any context, lower
+/// than for a symbol as many names are also everyday words
+const NAME_WEIGHT: f64 = 0.75;
+
+/// Confidence that an untagged word matching a symbol means the stock
+fn score(word: &str, symbol: &str, near_finance: bool) -> f64 {
+    let mut score = if STOPLIST.contains(&symbol) { 0.0 } else { 1.0 };
+
+    if word.chars().any(|c| c.is_alphabetic())
+        && !word.chars().any(|c| c.is_lowercase())
+    {
+        score += 0.5;
+    }
+
+    if near_finance {
+        score += 0.75;
+    }
+
+    if symbol.len() == 1 {
+        score -= 0.5;
+    }
+
+    score
+}
+
+/// Confidence that a capitalized word naming a company means the stock
+///
+/// The capital only counts as evidence away from the start of a
+/// sentence, where every word has one.
+fn name_score(word: &str, sentence_start: bool, near_finance: bool) -> f64 {
+    let bare = word
+        .trim_matches(|c: char| !c.is_alphabetic())
+        .to_uppercase();
+    let mut score = if STOPLIST.contains(&bare.as_str()) {
+        0.0
+    } else {
+        NAME_WEIGHT
+    };
+
+    if !sentence_start {
+        score += 0.25;
+    }
+
+    if near_finance {
+        score += 0.75;
+    }
+
+    score
+}
+
+fn is_finance_word(word: &str) -> bool {
+    let word = word
+        .trim_matches(|c: char| !c.is_alphabetic())
+        .to_lowercase();
+    FINANCE_WORDS.contains(&word.as_str())
+}
+
+/// Normalizes a user's spelling of a symbol, e.g. `$brk/b,` to `BRK.B`
+pub fn normalize_symbol(word: &str) -> String {
+    word.to_uppercase()
+        .chars()
+        .filter(|&c| LETTERS.contains(c) || CLASS_SEPARATORS.contains(c))
+        .map(|c| if CLASS_SEPARATORS.contains(c) { '.' } else { c })
+        .collect::<String>()
+        .trim_matches('.')
+        .to_string()
+}
+
+/// Shows user input as inline code, so mentions in it do not ping anyone
+/// when it is echoed back
+pub fn inline_code(text: &str) -> String {
+    format!("`{}`", text.replace('`', "'"))
+}
+
+/// Finds the listed form of a normalized symbol, trying both `.` and `-`
+/// as the share class separator
+pub fn resolve_symbol<F>(symbol: &str, is_listed: F) -> Option<String>
+where
+    F: Fn(&str) -> bool,
+{
+    if symbol.is_empty() {
+        return None;
+    }
+
+    if is_listed(symbol) {
+        return Some(symbol.to_string());
+    }
+
+    let dashed = symbol.replace('.', "-");
+    if dashed != symbol && is_listed(&dashed) {
+        return Some(dashed);
+    }
+
+    None
+}
+
 pub async fn extract_stocks(
     ctx: &Context,
     msg: &Message,
 ) -> Result<HashSet<String>> {
-    let symbols: HashSet<String>;
+    let universe: Arc<Universe>;
+    let threshold: f64;
+    let db: Option<Arc<Storage>>;
 
     {
         let data = ctx.data.read().await;
-        match data.get::<IEXClient>() {
-            Some(client) => match iex::cache::symbols(client).await {
-                Some(s) => symbols = s.iter().cloned().collect(),
-                None => symbols = HashSet::new(),
-            },
-            None => symbols = HashSet::new(),
+        threshold = data
+            .get::<DetectionThreshold>()
+            .copied()
+            .unwrap_or(DEFAULT_THRESHOLD);
+
+        universe = iex::cache::universe().unwrap_or_default();
+
+        db = data.get::<Database>().cloned();
+    }
+
+    let filter = match db {
+        Some(db) => {
+            let guild = msg.guild_id;
+            storage::blocking(db, move |db| db.listing_filter(guild)).await?
         }
+        None => ListingFilter::default(),
+    };
+
+    _extract_stocks(&universe, &filter, &msg.content, threshold)
+}
+
+/// The symbol of a company named by a capitalized word, as in "what's up
+/// with Nvidia", if exactly one eligible issue has that name
+fn company(
+    names: &NameIndex,
+    filter: &ListingFilter,
+    word: &str,
+) -> Option<String> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    let word = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix("\u{2019}s"))
+        .unwrap_or(word);
+
+    if word.len() < 3 || !word.starts_with(char::is_uppercase) {
+        return None;
     }
 
-    _extract_stocks(&symbols, &msg.content)
+    let matches: Vec<_> = names
+        .exact(word)
+        .into_iter()
+        .filter(|s| filter.allows(s))
+        .collect();
+
+    match matches.as_slice() {
+        [m] => Some(m.symbol.clone()),
+        _ => None,
+    }
 }
 
+/// Finds the stocks mentioned in a message
+///
+/// Words tagged with `$` are always taken and, if there are any, are the
+/// only stocks returned. Otherwise every word matching a symbol the
+/// `filter` allows, or capitalized and naming a single such issue, is
+/// scored and kept if it reaches `threshold`.
 fn _extract_stocks(
-    symbols: &HashSet<String>,
+    universe: &Universe,
+    filter: &ListingFilter,
     content: &str,
+    threshold: f64,
 ) -> Result<HashSet<String>> {
     let mut stocks: HashSet<String> = HashSet::new();
     let mut words: HashSet<String> = HashSet::new();
+    let eligible =
+        |s: &str| universe.get(s).map_or(false, |s| filter.allows(s));
+
+    let tokens: Vec<&str> = content.split_whitespace().collect();
+    let finance: Vec<bool> =
+        tokens.iter().map(|w| is_finance_word(w)).collect();
+
+    for (i, word) in tokens.iter().enumerate() {
+        let tagged = word.starts_with('$');
+        let window = i.saturating_sub(FINANCE_WINDOW)
+            ..tokens.len().min(i + FINANCE_WINDOW + 1);
+        let near_finance = window.filter(|&j| j != i).any(|j| finance[j]);
 
-    for word in content.split_whitespace() {
-        if word.starts_with('$') {
-            stocks.insert(
-                word.to_uppercase()
-                    .chars()
-                    .filter(|&c| LETTERS.contains(c))
-                    .collect(),
-            );
+        let symbol = match resolve_symbol(&normalize_symbol(word), |s| {
+            if tagged {
+                universe.contains(s)
+            } else {
+                eligible(s)
+            }
+        }) {
+            Some(s) => s,
+            None => {
+                if tagged {
+                    continue;
+                }
+
+                let sentence_start =
+                    i == 0 || tokens[i - 1].ends_with(|c| ".!?".contains(c));
+                if let Some(symbol) = company(universe.names(), filter, word) {
+                    if name_score(word, sentence_start, near_finance)
+                        >= threshold
+                    {
+                        words.insert(symbol);
+                    }
+                }
+                continue;
+            }
+        };
+
+        if tagged {
+            stocks.insert(symbol);
             continue;
         }
 
-        words.insert(
-            word.to_uppercase()
-                .chars()
-                .filter(|&c| LETTERS.contains(c))
-                .collect(),
-        );
+        if score(word, &symbol, near_finance) >= threshold {
+            words.insert(symbol);
+        }
     }
 
     if !stocks.is_empty() {
-        return Ok(symbols.intersection(&stocks).cloned().collect());
+        return Ok(stocks);
     }
 
-    let mut discovered: Vec<String> =
-        symbols.intersection(&words).cloned().collect();
+    let mut discovered: Vec<String> = words.into_iter().collect();
     discovered.sort_by(|a, b| b.len().cmp(&a.len()));
 
     if discovered.is_empty() {
@@ -77,13 +285,16 @@ fn _extract_stocks(
 
 #[cfg(test)]
 mod test {
-    use super::_extract_stocks;
+    use super::{_extract_stocks, DEFAULT_THRESHOLD};
+    use crate::filter::ListingFilter;
+    use crate::iex::{Symbol, Universe};
 
     use anyhow::Result;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
 
     const TICKERS: &'static [&'static str] = &["AAPL", "TSLA"];
+    const CLASSES: &'static [&'static str] = &["BRK.B", "BF.A", "BAC-L", "B"];
 
     #[rstest(
         tickers, content, result,
@@ -107,17 +318,115 @@ mod test {
         case::sentance_tagged_multiple(
             TICKERS, "$TSLA or $aapl?", &["AAPL", "TSLA"]),
         case::sentance_tagged_one(TICKERS, "TSLA or $aapl?", &["AAPL"]),
+
+        case::class(CLASSES, "$BRK.B", &["BRK.B"]),
+        case::class_dash(CLASSES, "$brk-b", &["BRK.B"]),
+        case::class_slash(CLASSES, "$BRK/B", &["BRK.B"]),
+        case::class_punctuation(CLASSES, "Bought $BRK.B.", &["BRK.B"]),
+        case::class_untagged(CLASSES, "bf.a or brk.b?", &["BF.A", "BRK.B"]),
+        case::class_listed_dash(CLASSES, "$BAC.L", &["BAC-L"]),
+        case::class_joined(CLASSES, "$BRKB", &[]),
     )]
     fn extract_stocks(
         tickers: &[&str],
         content: &str,
         result: &[&str],
     ) -> Result<()> {
-        let symbols = tickers.iter().map(|s| s.to_string()).collect();
+        let universe = universe(tickers, &[]);
         let result = result.iter().map(|s| s.to_string()).collect();
 
-        assert_eq!(_extract_stocks(&symbols, content)?, result);
+        assert_eq!(
+            _extract_stocks(
+                &universe,
+                &ListingFilter::default(),
+                content,
+                DEFAULT_THRESHOLD
+            )?,
+            result
+        );
 
         Ok(())
     }
+
+    const COMMON: &'static [&'static str] =
+        &["ALL", "AAPL", "F", "GOOD", "IT", "ON", "TSLA"];
+
+    #[rstest(
+        content, threshold, result,
+        case::stoplist("IT is ALL GOOD, ON the way", 1.0, &[]),
+        case::stoplist_lower("it is all good", 1.0, &[]),
+        case::stoplist_tagged("$ON is moving", 1.0, &["ON"]),
+        case::caps_finance("Bought ON calls", 1.0, &["ON"]),
+        case::lower_finance("bought calls on friday", 1.0, &[]),
+        case::finance_far(
+            "ON the way home I think I might buy", 1.0, &[]),
+        case::ordinary("aapl looks good", 1.0, &["AAPL"]),
+        case::single_lower("f this", 1.0, &[]),
+        case::single_caps("F shares", 1.0, &["F"]),
+        case::single_dropped("F and TSLA shares", 1.0, &["TSLA"]),
+
+        case::strict("aapl looks good", 1.5, &[]),
+        case::strict_caps("AAPL looks good", 1.5, &["AAPL"]),
+        case::loose("it is all good", 0.0, &["ALL", "GOOD", "IT"]),
+
+        case::company("what's up with Nvidia", 1.0, &["NVDA"]),
+        case::company_possessive("Nvidia's earnings", 1.0, &["NVDA"]),
+        case::company_lower("what's up with nvidia", 1.0, &[]),
+        case::company_ambiguous("Alphabet is up", 1.0, &[]),
+        case::company_tagged("$Nvidia", 1.0, &[]),
+        case::company_finance("Target shares are up", 1.0, &["TGT"]),
+        case::company_strict("what's up with Nvidia", 1.5, &[]),
+        case::plain_sentence("Target practice tonight", 1.0, &[]),
+        case::plain_after_sentence("Done. Target practice next", 1.0, &[]),
+        case::plain_stoplist("Home shares are up", 1.0, &[]),
+
+        case::warrant("NVDAW looks good", 1.0, &[]),
+        case::warrant_tagged("$NVDAW looks good", 1.0, &["NVDAW"]),
+    )]
+    fn detect(content: &str, threshold: f64, result: &[&str]) -> Result<()> {
+        let universe = universe(
+            COMMON,
+            &[
+                ("NVDA", "NVIDIA Corp", "cs"),
+                ("NVDAW", "NVIDIA Corp - Warrant", "wt"),
+                ("GOOGL", "Alphabet Inc - Class A", "cs"),
+                ("GOOG", "Alphabet Inc - Class C", "cs"),
+                ("TGT", "Target Corp", "cs"),
+                ("HMC", "Home Corp", "cs"),
+            ],
+        );
+        let filter = ListingFilter::default();
+        let result = result.iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(
+            _extract_stocks(&universe, &filter, content, threshold)?,
+            result
+        );
+
+        Ok(())
+    }
+
+    fn universe(
+        tickers: &[&str],
+        companies: &[(&str, &str, &str)],
+    ) -> Universe {
+        Universe::new(
+            tickers
+                .iter()
+                .map(|t| Symbol::test(t, "", "cs"))
+                .chain(companies.iter().map(|(t, n, i)| Symbol::test(t, n, i)))
+                .collect(),
+        )
+    }
+
+    #[rstest(
+        text,
+        result,
+        case::plain("2w", "`2w`"),
+        case::mention("@everyone", "`@everyone`"),
+        case::backtick("`@here`", "`'@here'`")
+    )]
+    fn inline_code(text: &str, result: &str) {
+        assert_eq!(super::inline_code(text), result);
+    }
 }